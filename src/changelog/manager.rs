@@ -42,28 +42,51 @@ pub fn update_changelog(
 pub fn get_new_changelog(name: &str, new_version: &str, changelog: Changelog) -> Result<String> {
     let mut new_changelog = String::new();
     new_changelog.push_str(format!("# {}", name).as_str());
-    new_changelog.push_str("\n");
+    new_changelog.push('\n');
     new_changelog.push_str(format!("## Version {}", new_version).as_str());
-    new_changelog.push_str("\n");
+    new_changelog.push('\n');
     if !changelog.features.is_empty() {
         new_changelog.push_str("### Features\n");
         new_changelog.push_str(&changelog.features);
-        new_changelog.push_str("\n");
+        new_changelog.push('\n');
     }
     if !changelog.fixes.is_empty() {
         new_changelog.push_str("### Fixes\n");
         new_changelog.push_str(&changelog.fixes);
-        new_changelog.push_str("\n");
+        new_changelog.push('\n');
     }
     if !changelog.perf.is_empty() {
         new_changelog.push_str("### Performance\n");
         new_changelog.push_str(&changelog.perf);
-        new_changelog.push_str("\n");
+        new_changelog.push('\n');
     }
 
     Ok(new_changelog)
 }
 
+/// Returns the section of a version in a changelog, without its heading.
+pub fn get_changelog_section(changelog: &str, version: &str) -> Option<String> {
+    let heading = format!("## Version {}", version);
+    let mut lines = changelog
+        .lines()
+        .skip_while(|line| line.trim_end() != heading);
+    lines.next()?;
+    let section = lines
+        .take_while(|line| !line.starts_with("## ") && !line.starts_with("# "))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let section = section.trim();
+    (!section.is_empty()).then(|| section.to_string())
+}
+
+/// Returns the version of the newest section of a changelog.
+pub fn get_latest_changelog_version(changelog: &str) -> Option<String> {
+    changelog.lines().find_map(|line| {
+        let version = line.strip_prefix("## Version ")?.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,4 +147,29 @@ mod tests {
 
         assert_eq!(result, new_changelog_body);
     }
+
+    #[test]
+    fn test_get_changelog_section() {
+        let changelog = "# api\n## Version 1.2.0\n### Features\n**api**: add orders\n\n## Version 1.1.0\n### Fixes\n**api**: fix totals\n";
+        assert_eq!(
+            get_changelog_section(changelog, "1.2.0").unwrap(),
+            "### Features\n**api**: add orders"
+        );
+        assert_eq!(
+            get_changelog_section(changelog, "1.1.0").unwrap(),
+            "### Fixes\n**api**: fix totals"
+        );
+        assert!(get_changelog_section(changelog, "1.0.0").is_none());
+        assert!(get_changelog_section(changelog, "1.2").is_none());
+    }
+
+    #[test]
+    fn test_get_latest_changelog_version() {
+        let changelog = "# billing\n## Version 1.4.0\n### Features\n**billing**: add invoices\n\n## Version 1.3.2\n";
+        assert_eq!(
+            get_latest_changelog_version(changelog),
+            Some("1.4.0".to_string())
+        );
+        assert_eq!(get_latest_changelog_version("# billing\n"), None);
+    }
 }
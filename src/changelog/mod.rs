@@ -1,2 +1,5 @@
-pub use self::manager::{get_new_changelog, update_changelog, Changelog};
+pub use self::manager::{
+    get_changelog_section, get_latest_changelog_version, get_new_changelog, update_changelog,
+    Changelog,
+};
 mod manager;
@@ -1,12 +1,63 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(value_name = "ENVIRONMENT", default_value = "production")]
     pub environment: String,
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub dry_run: bool,
     #[arg(long)]
     pub tag: bool,
+    /// Create lightweight tags instead of tags annotated with the release notes
+    #[arg(long, global = true)]
+    pub lightweight_tags: bool,
+    /// Release even if the working tree has uncommitted changes
+    #[arg(long)]
+    pub allow_dirty: bool,
+    /// Push the release branch, or the created tags with --tag
+    #[arg(long, global = true)]
+    pub push: bool,
+    /// Remote to push to, defaults to origin
+    #[arg(long, global = true)]
+    pub remote: Option<String>,
+    /// Sign the release commit and tags
+    #[arg(long, global = true)]
+    pub sign: bool,
+    /// Key used to sign, a GPG key id or the path to an SSH key
+    #[arg(long, global = true)]
+    pub signing_key: Option<String>,
+    /// Signing format: openpgp, ssh or x509
+    #[arg(long, global = true)]
+    pub signing_format: Option<String>,
+    /// Use the git executable instead of reading the repository in-process
+    #[arg(long, global = true)]
+    pub git_cli: bool,
+    /// Fetch the tags, and deepen a shallow clone until the release tags are reachable
+    #[arg(long, global = true)]
+    pub fetch: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Tag the current version of the packages that have never been released
+    Bootstrap,
+    /// Release on a branch reset from the base branch, push it and open or
+    /// update its GitHub pull request
+    ReleasePr {
+        /// Release branch, defaults to <ENVIRONMENT>-release
+        #[arg(long)]
+        branch: Option<String>,
+        /// Branch the release branch starts from and is merged into
+        #[arg(long, default_value = "main")]
+        base: String,
+        /// Pull request title, defaults to "<Environment> Release"
+        #[arg(long)]
+        title: Option<String>,
+        /// GitHub repository as owner/repo, defaults to $GITHUB_REPOSITORY
+        #[arg(long)]
+        repository: Option<String>,
+    },
 }
@@ -1,2 +1,2 @@
-pub use self::args::Args;
+pub use self::args::{Args, Command};
 mod args;
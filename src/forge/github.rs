@@ -0,0 +1,231 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+
+pub type ForgeResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+}
+
+/// Client of the GitHub REST API, scoped to one `owner/repo` repository.
+pub struct GitHub {
+    api_url: String,
+    repository: String,
+    token: String,
+}
+
+impl GitHub {
+    /// `api_url` is `https://api.github.com`, or the API of a GitHub Enterprise
+    /// server or test stand-in.
+    pub fn new(api_url: &str, repository: &str, token: &str) -> Self {
+        GitHub {
+            api_url: api_url.trim_end_matches('/').to_string(),
+            repository: repository.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Reads the token from `GITHUB_TOKEN`, the repository from `GITHUB_REPOSITORY`
+    /// unless given, and the API url from `GITHUB_API_URL`, as GitHub Actions sets them.
+    pub fn from_env(repository: Option<&str>) -> ForgeResult<Self> {
+        let token = env::var("GITHUB_TOKEN")
+            .map_err(|_| "GITHUB_TOKEN must be set to open the release pull request")?;
+        let repository = match repository {
+            Some(repository) => repository.to_string(),
+            None => env::var("GITHUB_REPOSITORY").map_err(|_| {
+                "Pass --repository or set GITHUB_REPOSITORY to open the release pull request"
+            })?,
+        };
+        if repository.split('/').count() != 2 {
+            return Err(
+                format!("Repository {} must be formatted as owner/repo", repository).into(),
+            );
+        }
+        let api_url =
+            env::var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".to_string());
+        Ok(Self::new(&api_url, &repository, &token))
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        ureq::request(
+            method,
+            &format!("{}/repos/{}{}", self.api_url, self.repository, path),
+        )
+        .set("Accept", "application/vnd.github+json")
+        .set("Authorization", &format!("Bearer {}", self.token))
+        .set("User-Agent", "releaser")
+    }
+
+    fn send(&self, request: ureq::Request, body: Option<Value>) -> ForgeResult<ureq::Response> {
+        let description = format!("{} {}", request.method(), request.url());
+        let result = match body {
+            Some(body) => request.send_json(body),
+            None => request.call(),
+        };
+        result.map_err(|e| match e {
+            ureq::Error::Status(status, response) => format!(
+                "{} failed with status {}: {}",
+                description,
+                status,
+                response.into_string().unwrap_or_default().trim()
+            )
+            .into(),
+            e => format!("{} failed: {}", description, e).into(),
+        })
+    }
+
+    /// Finds the open pull request of `head` into `base`.
+    pub fn find_pull_request(&self, head: &str, base: &str) -> ForgeResult<Option<PullRequest>> {
+        let owner = self.repository.split('/').next().unwrap_or_default();
+        let request = self
+            .request("GET", "/pulls")
+            .query("state", "open")
+            .query("head", &format!("{}:{}", owner, head))
+            .query("base", base);
+        let pull_requests: Vec<PullRequest> = self.send(request, None)?.into_json()?;
+        Ok(pull_requests.into_iter().next())
+    }
+
+    pub fn create_pull_request(
+        &self,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> ForgeResult<PullRequest> {
+        let body = json!({ "head": head, "base": base, "title": title, "body": body });
+        Ok(self
+            .send(self.request("POST", "/pulls"), Some(body))?
+            .into_json()?)
+    }
+
+    pub fn update_pull_request(
+        &self,
+        number: u64,
+        title: &str,
+        body: &str,
+    ) -> ForgeResult<PullRequest> {
+        let body = json!({ "title": title, "body": body });
+        let request = self.request("PATCH", &format!("/pulls/{}", number));
+        Ok(self.send(request, Some(body))?.into_json()?)
+    }
+
+    /// Updates the open pull request of `head` into `base`, or opens one.
+    /// Returns the pull request and whether it was created.
+    pub fn open_or_update_pull_request(
+        &self,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> ForgeResult<(PullRequest, bool)> {
+        match self.find_pull_request(head, base)? {
+            Some(pull_request) => Ok((
+                self.update_pull_request(pull_request.number, title, body)?,
+                false,
+            )),
+            None => Ok((self.create_pull_request(head, base, title, body)?, true)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::{self, JoinHandle};
+    use tiny_http::{Header, Response, Server};
+
+    /// Serves the given `(status, body)` responses in order from a local stand-in
+    /// of the API, and returns its url and the `<method> <url> <body>` requests.
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<String>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let mut request = server.recv().unwrap();
+                let mut content = String::new();
+                request.as_reader().read_to_string(&mut content).unwrap();
+                requests.push(
+                    format!("{} {} {}", request.method(), request.url(), content)
+                        .trim_end()
+                        .to_string(),
+                );
+                let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+                let response = Response::from_string(body)
+                    .with_status_code(status)
+                    .with_header(header);
+                request.respond(response).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_open_or_update_pull_request() {
+        let (url, server) = serve(vec![
+            (200, "[]"),
+            (
+                201,
+                r#"{"number": 7, "html_url": "https://github.com/acme/shop/pull/7"}"#,
+            ),
+        ]);
+        let github = GitHub::new(&url, "acme/shop", "token");
+        let (pull_request, created) = github
+            .open_or_update_pull_request(
+                "production-release",
+                "main",
+                "Production Release",
+                "## api - 1.1.0",
+            )
+            .unwrap();
+        assert!(created);
+        assert_eq!(pull_request.number, 7);
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /repos/acme/shop/pulls?state=open&head=acme%3Aproduction-release&base=main",
+                r###"POST /repos/acme/shop/pulls {"head":"production-release","base":"main","title":"Production Release","body":"## api - 1.1.0"}"###,
+            ]
+        );
+
+        let (url, server) = serve(vec![
+            (
+                200,
+                r#"[{"number": 7, "html_url": "https://github.com/acme/shop/pull/7"}]"#,
+            ),
+            (
+                200,
+                r#"{"number": 7, "html_url": "https://github.com/acme/shop/pull/7"}"#,
+            ),
+        ]);
+        let github = GitHub::new(&url, "acme/shop", "token");
+        let (pull_request, created) = github
+            .open_or_update_pull_request(
+                "production-release",
+                "main",
+                "Production Release",
+                "## api - 1.2.0",
+            )
+            .unwrap();
+        assert!(!created);
+        assert_eq!(pull_request.html_url, "https://github.com/acme/shop/pull/7");
+        assert_eq!(
+            server.join().unwrap()[1],
+            r###"PATCH /repos/acme/shop/pulls/7 {"title":"Production Release","body":"## api - 1.2.0"}"###
+        );
+
+        let (url, server) = serve(vec![(401, r#"{"message": "Bad credentials"}"#)]);
+        let github = GitHub::new(&url, "acme/shop", "token");
+        let error = github
+            .find_pull_request("production-release", "main")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("failed with status 401: {\"message\": \"Bad credentials\"}"));
+        server.join().unwrap();
+    }
+}
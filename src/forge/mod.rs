@@ -0,0 +1,2 @@
+pub use self::github::GitHub;
+mod github;
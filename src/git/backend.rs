@@ -0,0 +1,513 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use crate::Signing;
+
+pub type GitResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Every git operation releaser needs, so that the release logic can run against
+/// the `git` CLI or against an in-memory repository in tests.
+pub trait GitBackend {
+    /// Returns the sha of the commit HEAD points to.
+    fn head_sha(&self) -> GitResult<String>;
+    /// Lists the tags matching a pattern such as `api-v*`.
+    fn list_tags(&self, pattern: &str) -> GitResult<Vec<String>>;
+    /// Lists the tags matching a pattern that point to ancestors of `revision`.
+    fn merged_tags(&self, pattern: &str, revision: &str) -> GitResult<Vec<String>>;
+    /// Lists the commits of `from..to` touching `paths`, as `<sha> <subject>` lines.
+    fn log(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>>;
+    /// Lists the files under `paths` changed between two revisions.
+    fn diff_paths(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>>;
+    /// Lists the files a commit changes compared to its first parent.
+    fn commit_paths(&self, commit: &str) -> GitResult<Vec<String>>;
+    /// Lists the paths with uncommitted changes, untracked files included.
+    fn status(&self) -> GitResult<Vec<String>>;
+    /// Returns the sha of the oldest commit of HEAD touching `paths`.
+    fn first_commit(&self, paths: &[&str]) -> GitResult<Option<String>>;
+    /// Whether the repository is a shallow clone, missing the older history.
+    fn is_shallow(&self) -> GitResult<bool>;
+    /// Fetches the tags of a remote, without deepening a shallow clone.
+    fn fetch_tags(&self, remote: &str) -> GitResult<()>;
+    /// Fetches the tags of a remote, deepening a shallow clone by `depth` commits,
+    /// or fetching its whole history when `depth` is `None`.
+    fn fetch_history(&self, remote: &str, depth: Option<u32>) -> GitResult<()>;
+    /// Tags HEAD, with an annotation unless `message` is `None`.
+    fn create_tag(&self, tag: &str, message: Option<&str>) -> GitResult<()>;
+    /// Creates or resets `branch` to `start_point`, and checks it out.
+    fn checkout_branch(&self, branch: &str, start_point: &str) -> GitResult<()>;
+    fn add(&self, paths: &[&str]) -> GitResult<()>;
+    fn commit(&self, message: &str) -> GitResult<()>;
+    /// Returns the sha of a branch of a remote, empty when it does not exist.
+    fn remote_sha(&self, remote: &str, branch: &str) -> GitResult<String>;
+    /// Pushes refspecs to a remote, failing with the refs the remote rejected.
+    /// With a `lease`, overwrites the remote refs as long as they still point to
+    /// that sha, or do not exist when it is empty.
+    fn push(&self, remote: &str, refspecs: &[&str], lease: Option<&str>) -> GitResult<()>;
+}
+
+/// Runs the `git` executable in a working directory.
+pub struct CliGit {
+    dir: PathBuf,
+    signing: Signing,
+}
+
+impl CliGit {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        CliGit {
+            dir: dir.as_ref().to_path_buf(),
+            signing: Signing::default(),
+        }
+    }
+
+    pub fn with_signing(mut self, signing: Signing) -> Self {
+        self.signing = signing;
+        self
+    }
+
+    /// Configuration overrides selecting the signing key and format.
+    fn signing_config(&self) -> Vec<String> {
+        let mut config = Vec::new();
+        if let Some(format) = &self.signing.format {
+            config.extend(["-c".to_string(), format!("gpg.format={}", format)]);
+        }
+        if let Some(key) = &self.signing.key {
+            config.extend(["-c".to_string(), format!("user.signingkey={}", key)]);
+        }
+        config
+    }
+
+    fn run_signed(&self, args: &[&str], what: &str) -> GitResult<()> {
+        let config = self.signing_config();
+        let mut signed_args: Vec<&str> = config.iter().map(String::as_str).collect();
+        signed_args.extend(args);
+        self.run(&signed_args).map(|_| ()).map_err(|e| {
+            format!(
+                "Failed to sign the release {}, check the signing key and format: {}",
+                what, e
+            )
+            .into()
+        })
+    }
+
+    fn output(&self, args: &[&str]) -> GitResult<Output> {
+        Ok(Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            .output()
+            .map_err(|e| format!("Failed to execute git {}: {}", args[0], e))?)
+    }
+
+    fn run(&self, args: &[&str]) -> GitResult<String> {
+        let output = self.output(args)?;
+        if !output.status.success() {
+            return Err(format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Returns the full name of the remote ref a refspec pushes to.
+    fn push_destination(&self, refspec: &str) -> GitResult<String> {
+        let destination = refspec.rsplit(':').next().unwrap_or(refspec);
+        Ok(match destination {
+            "HEAD" => self.run(&["symbolic-ref", "HEAD"])?.trim().to_string(),
+            _ if destination.starts_with("refs/") => destination.to_string(),
+            _ => format!("refs/heads/{}", destination),
+        })
+    }
+
+    fn run_lines(&self, args: &[&str]) -> GitResult<Vec<String>> {
+        Ok(self
+            .run(args)?
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+impl GitBackend for CliGit {
+    fn head_sha(&self) -> GitResult<String> {
+        Ok(self.run(&["rev-parse", "HEAD"])?.trim().to_string())
+    }
+
+    fn list_tags(&self, pattern: &str) -> GitResult<Vec<String>> {
+        self.run_lines(&["tag", "-l", pattern])
+    }
+
+    fn merged_tags(&self, pattern: &str, revision: &str) -> GitResult<Vec<String>> {
+        self.run_lines(&["tag", "-l", pattern, "--merged", revision])
+    }
+
+    fn log(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let interval = format!("{}..{}", from, to);
+        let mut args = vec!["log", &interval, "--oneline", "--"];
+        args.extend(paths);
+        self.run_lines(&args)
+    }
+
+    fn diff_paths(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let mut args = vec!["diff", "--name-only", from, to, "--"];
+        args.extend(paths);
+        self.run_lines(&args)
+    }
+
+    fn commit_paths(&self, commit: &str) -> GitResult<Vec<String>> {
+        self.run_lines(&[
+            "diff-tree",
+            "--no-commit-id",
+            "--name-only",
+            "-r",
+            "--root",
+            "--diff-merges=first-parent",
+            commit,
+        ])
+    }
+
+    fn status(&self) -> GitResult<Vec<String>> {
+        // Entries are `XY <path>`, unquoted, followed by the original path for
+        // renames and copies
+        let output = self.run(&["status", "--porcelain", "-z"])?;
+        let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+        let mut paths = Vec::new();
+        while let Some(entry) = entries.next() {
+            let Some(path) = entry.get(3..) else {
+                continue;
+            };
+            if entry[..2].contains(['R', 'C']) {
+                entries.next();
+            }
+            paths.push(path.to_string());
+        }
+        Ok(paths)
+    }
+
+    fn first_commit(&self, paths: &[&str]) -> GitResult<Option<String>> {
+        let mut args = vec!["rev-list", "--reverse", "HEAD", "--"];
+        args.extend(paths);
+        Ok(self.run_lines(&args)?.into_iter().next())
+    }
+
+    fn is_shallow(&self) -> GitResult<bool> {
+        Ok(self.run(&["rev-parse", "--is-shallow-repository"])?.trim() == "true")
+    }
+
+    fn fetch_tags(&self, remote: &str) -> GitResult<()> {
+        self.run(&["fetch", "-q", "--tags", remote]).map(|_| ())
+    }
+
+    fn fetch_history(&self, remote: &str, depth: Option<u32>) -> GitResult<()> {
+        let deepen = depth.map(|depth| format!("--deepen={}", depth));
+        let mut args = vec!["fetch", "-q", "--tags"];
+        match &deepen {
+            Some(deepen) => args.push(deepen),
+            None if self.is_shallow()? => args.push("--unshallow"),
+            None => {}
+        }
+        args.push(remote);
+        self.run(&args).map(|_| ())
+    }
+
+    fn create_tag(&self, tag: &str, message: Option<&str>) -> GitResult<()> {
+        // The default cleanup strips the `### Features` headings as comments
+        let cleanup = "--cleanup=whitespace";
+        match message {
+            Some(message) if self.signing.tags => {
+                self.run_signed(&["tag", "-s", cleanup, tag, "-m", message], "tag")
+            }
+            Some(message) => self
+                .run(&["tag", "-a", cleanup, tag, "-m", message])
+                .map(|_| ()),
+            None if self.signing.tags => Err("Lightweight tags cannot be signed".into()),
+            None => self.run(&["tag", tag]).map(|_| ()),
+        }
+    }
+
+    fn checkout_branch(&self, branch: &str, start_point: &str) -> GitResult<()> {
+        self.run(&["checkout", "-q", "-B", branch, start_point])
+            .map(|_| ())
+    }
+
+    fn add(&self, paths: &[&str]) -> GitResult<()> {
+        let mut args = vec!["add", "--"];
+        args.extend(paths);
+        self.run(&args).map(|_| ())
+    }
+
+    fn commit(&self, message: &str) -> GitResult<()> {
+        if self.signing.commits {
+            return self.run_signed(&["commit", "-S", "-m", message], "commit");
+        }
+        self.run(&["commit", "-m", message]).map(|_| ())
+    }
+
+    fn remote_sha(&self, remote: &str, branch: &str) -> GitResult<String> {
+        let reference = format!("refs/heads/{}", branch);
+        let output = self.run(&["ls-remote", remote, &reference])?;
+        Ok(output
+            .lines()
+            .find_map(|line| {
+                let (sha, name) = line.split_once('\t')?;
+                (name == reference).then(|| sha.to_string())
+            })
+            .unwrap_or_default())
+    }
+
+    fn push(&self, remote: &str, refspecs: &[&str], lease: Option<&str>) -> GitResult<()> {
+        // A bare --force-with-lease compares with the remote-tracking refs, which
+        // CI clones often lack, so the expected value is explicit
+        let mut leases = Vec::new();
+        if let Some(lease) = lease {
+            for refspec in refspecs {
+                let destination = self.push_destination(refspec)?;
+                leases.push(format!("--force-with-lease={}:{}", destination, lease));
+            }
+        }
+        let mut args = vec!["push", "--porcelain"];
+        args.extend(leases.iter().map(String::as_str));
+        args.push(remote);
+        args.extend(refspecs);
+        let output = self.output(&args)?;
+
+        // Porcelain lines of rejected refs look like `!\t<from>:<to>\t[rejected] (reason)`
+        let rejected: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_prefix("!\t"))
+            .map(|line| {
+                let mut fields = line.split('\t');
+                let refspec = fields.next().unwrap_or_default();
+                let destination = refspec.rsplit(':').next().unwrap_or(refspec);
+                format!("{} {}", destination, fields.next().unwrap_or_default())
+            })
+            .collect();
+        if !rejected.is_empty() {
+            return Err(format!("{} rejected {}", remote, rejected.join(", ")).into());
+        }
+        if !output.status.success() {
+            return Err(format!(
+                "git push {} failed: {}",
+                remote,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_signing_failure() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let git = CliGit::new(root);
+        git.run(&["init", "-q"]).unwrap();
+        git.run(&["config", "user.name", "Test"]).unwrap();
+        git.run(&["config", "user.email", "test@example.com"])
+            .unwrap();
+        fs::write(root.join("VERSION"), "1.0.0").unwrap();
+        git.add(&["VERSION"]).unwrap();
+
+        let signed = CliGit::new(root).with_signing(Signing {
+            commits: true,
+            tags: true,
+            key: Some("releaser-missing-key".to_string()),
+            format: None,
+        });
+        assert_eq!(
+            signed.signing_config(),
+            vec!["-c", "user.signingkey=releaser-missing-key"]
+        );
+
+        let error = signed.commit("chore(release): bump packages").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Failed to sign the release commit"));
+
+        git.commit("chore(release): bump packages").unwrap();
+        let error = signed
+            .create_tag("api-v1.0.0", Some("api-v1.0.0"))
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Failed to sign the release tag"));
+        assert!(git.list_tags("api-v*").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_tag() {
+        let dir = tempdir().unwrap();
+        let git = CliGit::new(dir.path());
+        git.run(&["init", "-q"]).unwrap();
+        git.run(&["config", "user.name", "Test"]).unwrap();
+        git.run(&["config", "user.email", "test@example.com"])
+            .unwrap();
+        fs::write(dir.path().join("VERSION"), "1.1.0").unwrap();
+        git.add(&["VERSION"]).unwrap();
+        git.commit("chore(release): bump packages").unwrap();
+
+        let message = "api-v1.1.0\n\n### Features\n**api**: add orders";
+        git.create_tag("api-v1.1.0", Some(message)).unwrap();
+        assert_eq!(
+            git.run(&["tag", "-l", "--format=%(contents)", "api-v1.1.0"])
+                .unwrap()
+                .trim_end(),
+            message
+        );
+
+        git.create_tag("api-v1.1.1", None).unwrap();
+        assert_eq!(
+            git.run(&["cat-file", "-t", "api-v1.1.1"]).unwrap().trim(),
+            "commit"
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let dir = tempdir().unwrap();
+        let git = CliGit::new(dir.path());
+        git.run(&["init", "-q"]).unwrap();
+        git.run(&["config", "user.name", "Test"]).unwrap();
+        git.run(&["config", "user.email", "test@example.com"])
+            .unwrap();
+        fs::write(dir.path().join("old.txt"), "1.0.0").unwrap();
+        git.add(&["old.txt"]).unwrap();
+        git.commit("chore: init").unwrap();
+
+        git.run(&["mv", "old.txt", "new name é.txt"]).unwrap();
+        fs::write(dir.path().join("notes ü.md"), "draft").unwrap();
+        assert_eq!(git.status().unwrap(), vec!["new name é.txt", "notes ü.md"]);
+    }
+
+    #[test]
+    fn test_fetch_history() {
+        let dir = tempdir().unwrap();
+        let remote = dir.path().join("remote");
+        let root = dir.path().join("repository");
+        fs::create_dir_all(&remote).unwrap();
+
+        let remote_git = CliGit::new(&remote);
+        remote_git.run(&["init", "-q"]).unwrap();
+        remote_git.run(&["config", "user.name", "Test"]).unwrap();
+        remote_git
+            .run(&["config", "user.email", "test@example.com"])
+            .unwrap();
+        for version in ["1.0.0", "1.0.1", "1.0.2", "1.0.3"] {
+            fs::write(remote.join("VERSION"), version).unwrap();
+            remote_git.add(&["VERSION"]).unwrap();
+            remote_git.commit(version).unwrap();
+        }
+        remote_git.run(&["tag", "api-v1.0.0", "HEAD~3"]).unwrap();
+
+        let url = format!("file://{}", remote.display());
+        CliGit::new(dir.path())
+            .run(&["clone", "-q", "--depth", "1", &url, "repository"])
+            .unwrap();
+        let git = CliGit::new(&root);
+        assert!(git.is_shallow().unwrap());
+        assert!(git.list_tags("api-v*").unwrap().is_empty());
+
+        git.fetch_tags("origin").unwrap();
+        assert!(git.is_shallow().unwrap());
+        assert_eq!(git.list_tags("api-v*").unwrap(), vec!["api-v1.0.0"]);
+        assert!(git.merged_tags("api-v*", "HEAD").unwrap().is_empty());
+
+        git.fetch_history("origin", Some(1)).unwrap();
+        assert!(git.is_shallow().unwrap());
+        git.fetch_history("origin", None).unwrap();
+        assert!(!git.is_shallow().unwrap());
+        assert_eq!(
+            git.merged_tags("api-v*", "HEAD").unwrap(),
+            vec!["api-v1.0.0"]
+        );
+        // Fetching a complete history only fetches the tags
+        git.fetch_history("origin", None).unwrap();
+    }
+
+    #[test]
+    fn test_push() {
+        let dir = tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        let root = dir.path().join("repository");
+        fs::create_dir_all(&root).unwrap();
+        CliGit::new(dir.path())
+            .run(&["init", "-q", "--bare", remote.to_str().unwrap()])
+            .unwrap();
+
+        let git = CliGit::new(&root);
+        git.run(&["init", "-q", "-b", "release"]).unwrap();
+        git.run(&["config", "user.name", "Test"]).unwrap();
+        git.run(&["config", "user.email", "test@example.com"])
+            .unwrap();
+        git.run(&["remote", "add", "origin", remote.to_str().unwrap()])
+            .unwrap();
+        fs::write(root.join("VERSION"), "1.0.0").unwrap();
+        git.add(&["VERSION"]).unwrap();
+        git.commit("chore(release): bump packages").unwrap();
+        git.create_tag("api-v1.0.0", None).unwrap();
+
+        git.push("origin", &["HEAD", "refs/tags/api-v1.0.0"], Some(""))
+            .unwrap();
+        let remote_git = CliGit::new(&remote);
+        assert_eq!(remote_git.list_tags("*").unwrap(), vec!["api-v1.0.0"]);
+        assert_eq!(
+            remote_git.run(&["rev-parse", "release"]).unwrap(),
+            git.run(&["rev-parse", "HEAD"]).unwrap()
+        );
+
+        // The same tag on another commit is rejected by the remote
+        fs::write(root.join("VERSION"), "1.0.1").unwrap();
+        git.add(&["VERSION"]).unwrap();
+        git.commit("chore(release): bump packages").unwrap();
+        git.run(&["tag", "-f", "api-v1.0.0"]).unwrap();
+        let error = git
+            .push("origin", &["HEAD", "refs/tags/api-v1.0.0"], None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("origin rejected refs/tags/api-v1.0.0 [rejected]"));
+
+        // The lease is the remote branch read when resetting the release branch
+        let lease = git.remote_sha("origin", "release").unwrap();
+        assert_eq!(lease, git.run(&["rev-parse", "HEAD"]).unwrap().trim());
+        assert_eq!(git.remote_sha("origin", "missing").unwrap(), "");
+        let parent = git.run(&["rev-parse", "HEAD~1"]).unwrap();
+        git.checkout_branch("release", "HEAD~1").unwrap();
+        assert_eq!(git.run(&["rev-parse", "release"]).unwrap(), parent);
+
+        // The reset branch replaces the remote one, without any remote-tracking ref
+        git.run(&["update-ref", "-d", "refs/remotes/origin/release"])
+            .unwrap();
+        fs::write(root.join("VERSION"), "1.0.2").unwrap();
+        git.add(&["VERSION"]).unwrap();
+        git.commit("chore(release): bump packages").unwrap();
+        assert!(git.push("origin", &["HEAD"], None).is_err());
+        git.push("origin", &["HEAD"], Some(&lease)).unwrap();
+        assert_eq!(
+            remote_git.run(&["rev-parse", "release"]).unwrap(),
+            git.run(&["rev-parse", "HEAD"]).unwrap()
+        );
+
+        // Unless the remote branch moved on since then
+        let pushed = git.run(&["rev-parse", "HEAD"]).unwrap();
+        fs::write(root.join("VERSION"), "1.0.3").unwrap();
+        git.add(&["VERSION"]).unwrap();
+        git.commit("chore(release): bump packages").unwrap();
+        let error = git
+            .push("origin", &["HEAD"], Some(&lease))
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("origin rejected refs/heads/release [rejected] (stale info)"));
+        assert_eq!(remote_git.run(&["rev-parse", "release"]).unwrap(), pushed);
+    }
+}
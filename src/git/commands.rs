@@ -1,40 +1,253 @@
 use regex::Regex;
-use serde_json::Result;
 
-use crate::semver_compare;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+};
 
-pub fn get_latest_tag(name: &str, version: &str, environment: &str) -> Result<String> {
-    let tag_prefix = format!("{}-v", name);
+use super::{GitBackend, GitResult};
+use crate::{
+    log_info, log_success, log_warning, semver_compare, CommitMessage, PathRules, TagFormat,
+};
 
-    // Get all tags for this package
-    let output = std::process::Command::new("git")
-        .args(&["tag", "-l", &format!("{}*", tag_prefix)])
-        .output()
-        .expect("Failed to execute git tag command");
+/// Returns the tag of the latest release among the ones reachable from HEAD, so
+/// that releases from a maintenance branch ignore the tags of newer branches.
+pub fn get_latest_tag(
+    git: &dyn GitBackend,
+    tag_format: &TagFormat,
+    environment: &str,
+) -> GitResult<Option<String>> {
+    // Get the tags of this package merged into HEAD
+    let tags = git.merged_tags(&tag_format.pattern(), "HEAD")?;
+    Ok(find_latest_tag(&tags, tag_format, environment))
+}
+
+/// Returns the newest tag of a package when it is newer than the latest one
+/// reachable from HEAD, and so ignored.
+fn get_ignored_tag(
+    git: &dyn GitBackend,
+    tag_format: &TagFormat,
+    environment: &str,
+) -> GitResult<Option<String>> {
+    let latest_tag = get_latest_tag(git, tag_format, environment)?;
+    let all_tags = git.list_tags(&tag_format.pattern())?;
+    let Some(newest_tag) = find_latest_tag(&all_tags, tag_format, environment) else {
+        return Ok(None);
+    };
+    let is_newer = match &latest_tag {
+        Some(latest_tag) => {
+            semver_compare(
+                tag_format.version(&newest_tag).unwrap_or_default(),
+                tag_format.version(latest_tag).unwrap_or_default(),
+            ) == Ordering::Greater
+        }
+        None => true,
+    };
+    Ok(is_newer.then_some(newest_tag))
+}
+
+/// Warns when the newest tag of a package is ignored because it is not reachable
+/// from HEAD, such as when releasing from a maintenance branch.
+pub fn warn_unreachable_tags(
+    git: &dyn GitBackend,
+    tag_format: &TagFormat,
+    environment: &str,
+) -> GitResult<()> {
+    if let Some(tag) = get_ignored_tag(git, tag_format, environment)? {
+        log_warning(&format!(
+            "Ignoring tag {} which is not reachable from HEAD",
+            tag
+        ));
+    }
+    Ok(())
+}
+
+/// Names of the packages released before, none of whose release tags is
+/// reachable from HEAD.
+fn get_unreachable_releases(
+    git: &dyn GitBackend,
+    tag_formats: &[(String, TagFormat)],
+) -> GitResult<Vec<String>> {
+    let mut names = Vec::new();
+    for (name, tag_format) in tag_formats {
+        let pattern = tag_format.pattern();
+        if !git.list_tags(&pattern)?.is_empty() && git.merged_tags(&pattern, "HEAD")?.is_empty() {
+            names.push(name.clone());
+        }
+    }
+    Ok(names)
+}
+
+/// Commits fetched by the first deepening of a shallow clone, doubled on every
+/// round until the whole history is fetched.
+const FETCH_DEPTH: u32 = 64;
+const FETCH_ROUNDS: usize = 6;
+
+/// Makes sure the release tags are reachable before the history is analyzed, as a
+/// shallow clone would silently produce wrong versions. A shallow clone without
+/// any release tag cannot tell first releases from missing tags, so it fails, and
+/// otherwise only the packages with tags are checked. With `fetch`, fetches the
+/// tags first and deepens a shallow clone until they are reachable. Returns
+/// whether it fetched.
+pub fn ensure_release_history(
+    git: &dyn GitBackend,
+    tag_formats: &[(String, TagFormat)],
+    remote: &str,
+    fetch: bool,
+) -> GitResult<bool> {
+    if fetch {
+        git.fetch_tags(remote)?;
+        log_success(&format!("Fetched the tags of {}", remote));
+    }
+
+    let mut has_tags = false;
+    for (_, tag_format) in tag_formats {
+        has_tags = has_tags || !git.list_tags(&tag_format.pattern())?.is_empty();
+    }
+    let is_first_release = !tag_formats.is_empty() && !has_tags;
+    if !git.is_shallow()? {
+        if is_first_release {
+            log_warning(
+                "No release tag found, so every package is released for the first time. \
+                 If they were released before, pass --fetch or run `git fetch --tags`",
+            );
+        }
+        return Ok(fetch);
+    }
+
+    if is_first_release {
+        if !fetch {
+            return Err(
+                "The repository is a shallow clone without any release tag, so the new versions would be wrong. \
+                 Pass --fetch, run `git fetch --unshallow --tags` or set `fetch-depth: 0` on actions/checkout"
+                    .into(),
+            );
+        }
+        // First releases start from the commit that added each package
+        log_info("Shallow clone - Fetching the whole history");
+        git.fetch_history(remote, None)?;
+        return Ok(true);
+    }
 
-    let tags = String::from_utf8_lossy(&output.stdout);
+    let unreachable = get_unreachable_releases(git, tag_formats)?;
+    if unreachable.is_empty() {
+        return Ok(fetch);
+    }
+    if !fetch {
+        return Err(format!(
+            "The repository is a shallow clone and no release of {} is reachable from HEAD, so the new versions would be wrong. \
+             Pass --fetch, run `git fetch --unshallow --tags` or set `fetch-depth: 0` on actions/checkout",
+            unreachable.join(", ")
+        )
+        .into());
+    }
 
-    // Filter and sort tags based on environment
-    let latest_tag = tags
-        .lines()
-        .filter(|tag| {
+    let mut depth = FETCH_DEPTH;
+    for _ in 0..FETCH_ROUNDS {
+        log_info(&format!("Shallow clone - Fetching {} more commits", depth));
+        git.fetch_history(remote, Some(depth))?;
+        if !git.is_shallow()? || get_unreachable_releases(git, tag_formats)?.is_empty() {
+            return Ok(true);
+        }
+        depth *= 2;
+    }
+    log_info("Shallow clone - Fetching the whole history");
+    git.fetch_history(remote, None)?;
+    Ok(true)
+}
+
+fn find_latest_tag(tags: &[String], tag_format: &TagFormat, environment: &str) -> Option<String> {
+    tags.iter()
+        .filter_map(|tag| Some((tag, tag_format.version(tag)?)))
+        .filter(|(_, tag_version)| {
             if environment == "production" {
-                !tag.contains("-beta")
+                !tag_version.contains("-beta")
             } else {
                 true // In non-production, consider all tags
             }
         })
-        .max_by(|a, b| {
-            // Custom comparison for semantic versioning
-            let version_a = a.trim_start_matches(&tag_prefix);
-            let version_b = b.trim_start_matches(&tag_prefix);
-            semver_compare(version_a, version_b)
-        });
+        // Custom comparison for semantic versioning
+        .max_by(|(_, version_a), (_, version_b)| semver_compare(version_a, version_b))
+        .map(|(tag, _)| tag.to_string())
+}
+
+/// Lists the files changed between two revisions whose changes release a package.
+pub fn get_changed_files(
+    git: &dyn GitBackend,
+    rules: &PathRules,
+    from: &str,
+    to: &str,
+) -> GitResult<Vec<String>> {
+    let files = git.diff_paths(from, to, &rules.pathspecs())?;
+    Ok(files
+        .into_iter()
+        .filter(|file| !rules.filters_files() || rules.matches(file))
+        .collect())
+}
 
-    match latest_tag {
-        Some(tag) => Ok(tag.to_string()),
-        None => Ok(format!("{}-v{}", name, version)), // Return current version if no tags found
+/// Lists the commits of `from..to` releasing a package, as `<sha> <subject>` lines.
+pub fn get_package_log(
+    git: &dyn GitBackend,
+    rules: &PathRules,
+    from: &str,
+    to: &str,
+) -> GitResult<Vec<String>> {
+    let lines = git.log(from, to, &rules.pathspecs())?;
+    if !rules.filters_files() {
+        return Ok(lines);
     }
+    let mut package_lines = Vec::new();
+    for line in lines {
+        let sha = line.split_whitespace().next().unwrap_or_default();
+        if git
+            .commit_paths(sha)?
+            .iter()
+            .any(|path| rules.matches(path))
+        {
+            package_lines.push(line);
+        }
+    }
+    Ok(package_lines)
+}
+
+/// Builds the release commit message, listing the packages sorted by name.
+pub fn format_release_commit_message(
+    template: &CommitMessage,
+    versions: &HashMap<String, String>,
+) -> String {
+    let versions: BTreeMap<&String, &String> = versions.iter().collect();
+    let packages = versions
+        .iter()
+        .map(|(name, version)| format!("{}@{}", name, version))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let render = |template: &str| {
+        template
+            .replace("{packages}", &packages)
+            .replace("{count}", &versions.len().to_string())
+    };
+
+    let mut message = render(&template.subject);
+    if template.skip_ci {
+        message.push_str(" [skip ci]");
+    }
+    message.push_str("\n\n");
+    for (name, version) in &versions {
+        let line = template
+            .line
+            .replace("{name}", name)
+            .replace("{version}", version);
+        message.push_str(&line);
+        message.push('\n');
+    }
+    if !template.trailers.is_empty() {
+        message.push('\n');
+        for trailer in &template.trailers {
+            message.push_str(&render(trailer));
+            message.push('\n');
+        }
+    }
+    message
 }
 
 pub fn format_commit_message(input: &str) -> String {
@@ -53,21 +266,7 @@ pub fn format_commit_message(input: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockall::{mock, predicate::*};
-
-    mock! {
-        FileSystem {
-            fn read_to_string(&self, path: &str) -> std::io::Result<String>;
-            fn write(&self, path: &str, contents: &str) -> std::io::Result<()>;
-        }
-    }
-
-    mock! {
-        GitCommand {
-            fn diff(&self, last_tag: &str, path: &str) -> String;
-            fn log(&self, interval: &str) -> String;
-        }
-    }
+    use crate::{git::InMemoryGit, Manifest};
 
     #[test]
     fn test_format_commit_message() {
@@ -89,53 +288,229 @@ mod tests {
 
     #[test]
     fn test_get_latest_tag() {
-        // Setup test environment
-        let setup_git_tags = |tags: &[&str]| {
-            // Clear existing tags
-            let _ = std::process::Command::new("git")
-                .args(&["tag", "-d"])
-                .args(tags)
-                .output();
-
-            // Create new tags
-            for tag in tags {
-                let _ = std::process::Command::new("git")
-                    .args(&["tag", tag])
-                    .output();
-            }
-        };
-
-        // Test case 1: Production environment with mixed tags
-        let tags = &[
+        let git = InMemoryGit::new();
+        git.commit_files("chore: init", &["package.json"]);
+        for tag in [
             "package-a-v1.0.0",
             "package-a-v1.0.1-beta",
             "package-a-v1.0.1",
             "package-a-v1.1.0-beta.1",
-        ];
-        setup_git_tags(tags);
+        ] {
+            git.tag(tag);
+        }
+
+        let package_a = TagFormat::new("{name}-v{version}", "package-a", ".").unwrap();
+        let package_b = TagFormat::new("{name}-v{version}", "package-b", ".").unwrap();
+
+        // Test case 1: Production environment with mixed tags
+        assert_eq!(
+            get_latest_tag(&git, &package_a, "production").unwrap(),
+            Some("package-a-v1.0.1".to_string())
+        );
 
         assert_eq!(
-            get_latest_tag("package-a", "1.0.0", "production").unwrap(),
-            "package-a-v1.0.1"
+            get_ignored_tag(&git, &package_a, "production").unwrap(),
+            None
         );
 
         // Test case 2: Staging environment with beta tags
         assert_eq!(
-            get_latest_tag("package-a", "1.0.0", "staging").unwrap(),
-            "package-a-v1.1.0-beta.1"
+            get_latest_tag(&git, &package_a, "staging").unwrap(),
+            Some("package-a-v1.1.0-beta.1".to_string())
         );
 
         // Test case 3: No tags exist
-        let no_tags_package = "package-b";
         assert_eq!(
-            get_latest_tag(no_tags_package, "1.0.0", "production").unwrap(),
-            format!("{}-v1.0.0", no_tags_package)
+            get_latest_tag(&git, &package_b, "production").unwrap(),
+            None
+        );
+
+        // Test case 4: Tags of other packages and non-version tags are ignored
+        git.tag("v2.0.0");
+        git.tag("very-latest");
+        let single = TagFormat::new("v{version}", "package-a", ".").unwrap();
+        assert_eq!(
+            get_latest_tag(&git, &single, "production").unwrap(),
+            Some("v2.0.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_latest_tag_ignores_unreachable_tags() {
+        let git = InMemoryGit::new();
+        git.commit_files("chore: init", &["api/package.json"]);
+        git.tag("api-v1.0.0");
+        git.commit_files("feat(api): add orders", &["api/index.js"]);
+        git.tag("api-v1.1.0");
+
+        // A hotfix released from a maintenance branch
+        git.checkout("api-v1.0.0");
+        git.commit_files("fix(api): fix totals", &["api/index.js"]);
+        git.tag("api-v1.0.1");
+        git.commit_files("fix(api): fix rounding", &["api/index.js"]);
+
+        let api = TagFormat::new("{name}-v{version}", "api", "api").unwrap();
+        assert_eq!(
+            get_latest_tag(&git, &api, "production").unwrap(),
+            Some("api-v1.0.1".to_string())
+        );
+        assert_eq!(
+            get_ignored_tag(&git, &api, "production").unwrap(),
+            Some("api-v1.1.0".to_string())
+        );
+        assert_eq!(
+            git.log("api-v1.0.1", "HEAD", &["api"]).unwrap(),
+            vec!["0000004 fix(api): fix rounding"]
+        );
+    }
+
+    #[test]
+    fn test_path_rules_select_changes() {
+        let manifest: Manifest = serde_json::from_value(serde_json::json!({
+            "sharedPaths": [{"path": "tsconfig.base.json", "packages": ["web"]}],
+            "packages": [
+                {"path": "api", "exclude": ["**/*.test.js", "README.md"]},
+                {"path": "web"}
+            ]
+        }))
+        .unwrap();
+        let api = PathRules::new(&manifest.packages[0], "api", &manifest).unwrap();
+        let web = PathRules::new(&manifest.packages[1], "web", &manifest).unwrap();
+
+        let git = InMemoryGit::new();
+        git.commit_files(
+            "chore: initial commit",
+            &["api/package.json", "web/package.json"],
+        );
+        git.tag("v1.0.0");
+        git.commit_files(
+            "feat(api): test the orders endpoint",
+            &["api/src/orders.test.js", "api/README.md"],
+        );
+        git.commit_files("fix(config): target es2022", &["tsconfig.base.json"]);
+        git.commit_files("fix(api): round totals", &["api/src/orders.js"]);
+
+        assert_eq!(
+            get_changed_files(&git, &api, "v1.0.0", "HEAD").unwrap(),
+            vec!["api/src/orders.js"]
+        );
+        assert_eq!(
+            get_package_log(&git, &api, "v1.0.0", "HEAD").unwrap(),
+            vec!["0000004 fix(api): round totals"]
+        );
+        assert_eq!(
+            get_changed_files(&git, &web, "v1.0.0", "HEAD").unwrap(),
+            vec!["tsconfig.base.json"]
         );
+        assert_eq!(
+            get_package_log(&git, &web, "v1.0.0", "HEAD").unwrap(),
+            vec!["0000003 fix(config): target es2022"]
+        );
+    }
+
+    #[test]
+    fn test_ensure_release_history() {
+        let api = TagFormat::new("{name}-v{version}", "api", "api").unwrap();
+        let web = TagFormat::new("{name}-v{version}", "web", "web").unwrap();
+        let tag_formats = vec![("api".to_string(), api), ("web".to_string(), web)];
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &["api/package.json"]);
+        git.tag("api-v1.0.0");
+        for index in 0..3 {
+            git.commit_files("fix(api): fix totals", &[&format!("api/{}.js", index)]);
+        }
+        assert!(!ensure_release_history(&git, &tag_formats, "origin", false).unwrap());
 
-        // Cleanup
-        let _ = std::process::Command::new("git")
-            .args(&["tag", "-d"])
-            .args(tags)
-            .output();
+        // The tagged commit is part of the shallow history
+        git.make_shallow(4);
+        assert!(!ensure_release_history(&git, &tag_formats, "origin", false).unwrap());
+
+        // web, which has never been released, does not need any history
+        git.make_shallow(1);
+        let error = ensure_release_history(&git, &tag_formats, "origin", false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with(
+            "The repository is a shallow clone and no release of api is reachable from HEAD"
+        ));
+        assert!(git.is_shallow().unwrap());
+
+        assert!(ensure_release_history(&git, &tag_formats, "origin", true).unwrap());
+        assert!(!git.is_shallow().unwrap());
+        assert_eq!(
+            get_latest_tag(&git, &tag_formats[0].1, "production").unwrap(),
+            Some("api-v1.0.0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ensure_release_history_fetches_tags_first() {
+        let api = TagFormat::new("{name}-v{version}", "api", "api").unwrap();
+        let tag_formats = vec![("api".to_string(), api)];
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &["api/package.json"]);
+        git.remote_tag("api-v1.0.0");
+        for index in 0..3 {
+            git.commit_files("fix(api): fix totals", &[&format!("api/{}.js", index)]);
+        }
+        git.make_shallow(1);
+
+        // Without its tags, the clone could be mistaken for a first release
+        let error = ensure_release_history(&git, &tag_formats, "origin", false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("The repository is a shallow clone without any release tag"));
+
+        assert!(ensure_release_history(&git, &tag_formats, "origin", true).unwrap());
+        assert!(!git.is_shallow().unwrap());
+        assert_eq!(
+            git.merged_tags("api-v*", "HEAD").unwrap(),
+            vec!["api-v1.0.0"]
+        );
+    }
+
+    #[test]
+    fn test_ensure_release_history_of_first_releases() {
+        let api = TagFormat::new("{name}-v{version}", "api", "api").unwrap();
+        let tag_formats = vec![("api".to_string(), api)];
+
+        let git = InMemoryGit::new();
+        git.commit_files("feat(api): initial version", &["api/package.json"]);
+        git.commit_files("feat(api): add orders", &["api/index.js"]);
+        assert!(!ensure_release_history(&git, &tag_formats, "origin", false).unwrap());
+
+        git.make_shallow(1);
+        assert!(ensure_release_history(&git, &tag_formats, "origin", false).is_err());
+        assert!(ensure_release_history(&git, &tag_formats, "origin", true).unwrap());
+        assert!(!git.is_shallow().unwrap());
+        assert_eq!(
+            git.first_commit(&["api"]).unwrap(),
+            Some("0000001".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_release_commit_message() {
+        let versions = HashMap::from([
+            ("web".to_string(), "2.0.1".to_string()),
+            ("api".to_string(), "1.1.0".to_string()),
+        ]);
+        assert_eq!(
+            format_release_commit_message(&CommitMessage::default(), &versions),
+            "chore(release): bump packages\n\n- api: 1.1.0\n- web: 2.0.1\n"
+        );
+
+        let template = CommitMessage {
+            subject: "chore: release {count} packages".to_string(),
+            line: "* {name} v{version}".to_string(),
+            skip_ci: true,
+            trailers: vec!["Release-Packages: {packages}".to_string()],
+        };
+        assert_eq!(
+            format_release_commit_message(&template, &versions),
+            "chore: release 2 packages [skip ci]\n\n* api v1.1.0\n* web v2.0.1\n\nRelease-Packages: api@1.1.0, web@2.0.1\n"
+        );
     }
 }
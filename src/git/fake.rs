@@ -0,0 +1,453 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{GitBackend, GitResult};
+
+struct FakeCommit {
+    sha: String,
+    message: String,
+    paths: Vec<String>,
+    parent: Option<usize>,
+}
+
+#[derive(Default)]
+struct FakeRepository {
+    commits: Vec<FakeCommit>,
+    head: Option<usize>,
+    branch: Option<String>,
+    branches: HashMap<String, usize>,
+    tags: HashMap<String, usize>,
+    /// Tags of the remote which are not fetched yet
+    remote_tags: HashMap<String, usize>,
+    tag_messages: HashMap<String, String>,
+    pushed: Vec<String>,
+    /// Branches of the remote
+    remote_branches: HashMap<String, usize>,
+    /// Number of commits of a shallow clone
+    depth: Option<usize>,
+    modified: Vec<String>,
+    staged: Vec<String>,
+}
+
+impl FakeRepository {
+    /// Commits reachable from `index`, newest first, down to the shallow boundary.
+    fn ancestors(&self, index: usize) -> Vec<usize> {
+        let mut ancestors = vec![index];
+        while let Some(parent) = self.commits[*ancestors.last().unwrap()].parent {
+            if self.depth.is_some_and(|depth| ancestors.len() >= depth) {
+                break;
+            }
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+}
+
+/// In-memory git repository without merges, for deterministic tests.
+#[derive(Default)]
+pub struct InMemoryGit {
+    repository: RefCell<FakeRepository>,
+}
+
+fn touches(commit_path: &str, paths: &[&str]) -> bool {
+    paths.is_empty()
+        || paths.iter().any(|path| {
+            let path = path.trim_end_matches('/');
+            path == "." || commit_path == path || commit_path.starts_with(&format!("{}/", path))
+        })
+}
+
+/// Supports a single `*` wildcard, which is enough for tag formats.
+fn matches_pattern(tag: &str, pattern: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            tag.len() >= prefix.len() + suffix.len()
+                && tag.starts_with(prefix)
+                && tag.ends_with(suffix)
+        }
+        None => tag == pattern,
+    }
+}
+
+impl InMemoryGit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a commit on top of HEAD and returns its sha.
+    pub fn commit_files(&self, message: &str, paths: &[&str]) -> String {
+        let mut repository = self.repository.borrow_mut();
+        let sha = format!("{:07x}", repository.commits.len() + 1);
+        let parent = repository.head;
+        repository.commits.push(FakeCommit {
+            sha: sha.clone(),
+            message: message.to_string(),
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+            parent,
+        });
+        let head = repository.commits.len() - 1;
+        repository.head = Some(head);
+        if let Some(branch) = repository.branch.clone() {
+            repository.branches.insert(branch, head);
+        }
+        sha
+    }
+
+    /// Tags HEAD, without going through `create_tag`.
+    pub fn tag(&self, tag: &str) {
+        let mut repository = self.repository.borrow_mut();
+        let head = repository.head.unwrap_or_default();
+        repository.tags.insert(tag.to_string(), head);
+    }
+
+    /// Tags HEAD on the remote only, until the tags are fetched.
+    pub fn remote_tag(&self, tag: &str) {
+        let mut repository = self.repository.borrow_mut();
+        let head = repository.head.unwrap_or_default();
+        repository.remote_tags.insert(tag.to_string(), head);
+    }
+
+    /// Returns the annotation of a tag, `None` for lightweight tags.
+    pub fn tag_message(&self, tag: &str) -> Option<String> {
+        self.repository.borrow().tag_messages.get(tag).cloned()
+    }
+
+    /// Marks a file of the working tree as modified.
+    pub fn modify(&self, path: &str) {
+        self.repository.borrow_mut().modified.push(path.to_string());
+    }
+
+    /// Hides the history older than the `depth` last commits, like a shallow clone.
+    pub fn make_shallow(&self, depth: usize) {
+        self.repository.borrow_mut().depth = Some(depth);
+    }
+
+    /// Points a branch of the remote to HEAD, as if pushed from another clone.
+    pub fn remote_branch(&self, branch: &str) {
+        let mut repository = self.repository.borrow_mut();
+        let head = repository.head.unwrap_or_default();
+        repository.remote_branches.insert(branch.to_string(), head);
+    }
+
+    /// Returns the refspecs pushed so far, as `<remote> <refspec>`.
+    pub fn pushed(&self) -> Vec<String> {
+        self.repository.borrow().pushed.clone()
+    }
+
+    /// Moves HEAD to a revision. The next commits advance it if it is a branch,
+    /// or start a new branch otherwise.
+    pub fn checkout(&self, revision: &str) {
+        let mut repository = self.repository.borrow_mut();
+        let index = Self::resolve(&repository, revision).unwrap();
+        repository.head = Some(index);
+        repository.branch = repository
+            .branches
+            .contains_key(revision)
+            .then(|| revision.to_string());
+    }
+
+    /// Names the branch HEAD is on, so that it can be checked out later.
+    pub fn branch(&self, branch: &str) {
+        let mut repository = self.repository.borrow_mut();
+        let head = repository.head.unwrap_or_default();
+        repository.branches.insert(branch.to_string(), head);
+        repository.branch = Some(branch.to_string());
+    }
+
+    pub fn current_branch(&self) -> Option<String> {
+        self.repository.borrow().branch.clone()
+    }
+
+    pub fn commit_messages(&self) -> Vec<String> {
+        let repository = self.repository.borrow();
+        repository
+            .head
+            .map(|head| repository.ancestors(head))
+            .unwrap_or_default()
+            .iter()
+            .rev()
+            .map(|index| repository.commits[*index].message.clone())
+            .collect()
+    }
+
+    fn resolve(repository: &FakeRepository, revision: &str) -> GitResult<usize> {
+        if revision == "HEAD" {
+            return repository
+                .head
+                .ok_or_else(|| "HEAD does not point to a commit".into());
+        }
+        if let Some(index) = repository
+            .tags
+            .get(revision)
+            .or(repository.branches.get(revision))
+        {
+            return Ok(*index);
+        }
+        repository
+            .commits
+            .iter()
+            .position(|commit| commit.sha == revision)
+            .ok_or_else(|| format!("unknown revision {}", revision).into())
+    }
+
+    /// Commits of `from..to`, newest first.
+    fn range<'a>(
+        repository: &'a FakeRepository,
+        from: &str,
+        to: &str,
+    ) -> GitResult<Vec<&'a FakeCommit>> {
+        let excluded = repository.ancestors(Self::resolve(repository, from)?);
+        Ok(repository
+            .ancestors(Self::resolve(repository, to)?)
+            .into_iter()
+            .filter(|index| !excluded.contains(index))
+            .map(|index| &repository.commits[index])
+            .collect())
+    }
+}
+
+impl GitBackend for InMemoryGit {
+    fn head_sha(&self) -> GitResult<String> {
+        let repository = self.repository.borrow();
+        Ok(repository.commits[Self::resolve(&repository, "HEAD")?]
+            .sha
+            .clone())
+    }
+
+    fn list_tags(&self, pattern: &str) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        let mut tags: Vec<String> = repository
+            .tags
+            .keys()
+            .filter(|tag| matches_pattern(tag, pattern))
+            .cloned()
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn merged_tags(&self, pattern: &str, revision: &str) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        let reachable = repository.ancestors(Self::resolve(&repository, revision)?);
+        let mut tags: Vec<String> = repository
+            .tags
+            .iter()
+            .filter(|(tag, index)| matches_pattern(tag, pattern) && reachable.contains(index))
+            .map(|(tag, _)| tag.clone())
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn log(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        Ok(Self::range(&repository, from, to)?
+            .iter()
+            .filter(|commit| commit.paths.iter().any(|path| touches(path, paths)))
+            .map(|commit| format!("{} {}", commit.sha, commit.message))
+            .collect())
+    }
+
+    fn diff_paths(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        let mut changed: Vec<String> = Self::range(&repository, from, to)?
+            .iter()
+            .flat_map(|commit| commit.paths.iter())
+            .filter(|path| touches(path, paths))
+            .cloned()
+            .collect();
+        changed.sort();
+        changed.dedup();
+        Ok(changed)
+    }
+
+    fn commit_paths(&self, commit: &str) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        let mut paths = repository.commits[Self::resolve(&repository, commit)?]
+            .paths
+            .clone();
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn status(&self) -> GitResult<Vec<String>> {
+        let repository = self.repository.borrow();
+        Ok(repository
+            .modified
+            .iter()
+            .chain(&repository.staged)
+            .cloned()
+            .collect())
+    }
+
+    fn first_commit(&self, paths: &[&str]) -> GitResult<Option<String>> {
+        let repository = self.repository.borrow();
+        let head = Self::resolve(&repository, "HEAD")?;
+        Ok(repository
+            .ancestors(head)
+            .into_iter()
+            .rev()
+            .map(|index| &repository.commits[index])
+            .find(|commit| commit.paths.iter().any(|path| touches(path, paths)))
+            .map(|commit| commit.sha.clone()))
+    }
+
+    fn is_shallow(&self) -> GitResult<bool> {
+        Ok(self.repository.borrow().depth.is_some())
+    }
+
+    fn fetch_tags(&self, _remote: &str) -> GitResult<()> {
+        let mut repository = self.repository.borrow_mut();
+        let remote_tags: Vec<_> = repository.remote_tags.drain().collect();
+        repository.tags.extend(remote_tags);
+        Ok(())
+    }
+
+    fn fetch_history(&self, remote: &str, depth: Option<u32>) -> GitResult<()> {
+        self.fetch_tags(remote)?;
+        let mut repository = self.repository.borrow_mut();
+        let commits = repository.commits.len();
+        repository.depth = match (repository.depth, depth) {
+            (Some(current), Some(depth)) if current + (depth as usize) < commits => {
+                Some(current + depth as usize)
+            }
+            _ => None,
+        };
+        Ok(())
+    }
+
+    fn create_tag(&self, tag: &str, message: Option<&str>) -> GitResult<()> {
+        if self.repository.borrow().tags.contains_key(tag) {
+            return Err(format!("tag '{}' already exists", tag).into());
+        }
+        self.tag(tag);
+        if let Some(message) = message {
+            let mut repository = self.repository.borrow_mut();
+            repository
+                .tag_messages
+                .insert(tag.to_string(), message.to_string());
+        }
+        Ok(())
+    }
+
+    fn checkout_branch(&self, branch: &str, start_point: &str) -> GitResult<()> {
+        let index = Self::resolve(&self.repository.borrow(), start_point)?;
+        let mut repository = self.repository.borrow_mut();
+        repository.head = Some(index);
+        repository.branches.insert(branch.to_string(), index);
+        repository.branch = Some(branch.to_string());
+        Ok(())
+    }
+
+    fn add(&self, paths: &[&str]) -> GitResult<()> {
+        let mut repository = self.repository.borrow_mut();
+        repository
+            .modified
+            .retain(|path| !paths.contains(&path.as_str()));
+        repository
+            .staged
+            .extend(paths.iter().map(|path| path.to_string()));
+        Ok(())
+    }
+
+    fn commit(&self, message: &str) -> GitResult<()> {
+        let staged = std::mem::take(&mut self.repository.borrow_mut().staged);
+        if staged.is_empty() {
+            return Err("nothing to commit".into());
+        }
+        let staged: Vec<&str> = staged.iter().map(String::as_str).collect();
+        self.commit_files(message, &staged);
+        Ok(())
+    }
+
+    fn remote_sha(&self, _remote: &str, branch: &str) -> GitResult<String> {
+        let repository = self.repository.borrow();
+        Ok(repository
+            .remote_branches
+            .get(branch)
+            .map(|index| repository.commits[*index].sha.clone())
+            .unwrap_or_default())
+    }
+
+    fn push(&self, remote: &str, refspecs: &[&str], lease: Option<&str>) -> GitResult<()> {
+        let mut repository = self.repository.borrow_mut();
+        // Only HEAD pushes a branch, the other refspecs are tags
+        if let (true, Some(branch), Some(head)) = (
+            refspecs.contains(&"HEAD"),
+            repository.branch.clone(),
+            repository.head,
+        ) {
+            let remote_head = repository.remote_branches.get(&branch).copied();
+            let remote_sha = remote_head
+                .map(|index| repository.commits[index].sha.as_str())
+                .unwrap_or_default();
+            let reason = match lease {
+                Some(lease) if lease != remote_sha => Some("stale info"),
+                Some(_) => None,
+                None if remote_head.is_some_and(|remote_head| {
+                    !repository.ancestors(head).contains(&remote_head)
+                }) =>
+                {
+                    Some("non-fast-forward")
+                }
+                None => None,
+            };
+            if let Some(reason) = reason {
+                return Err(format!(
+                    "{} rejected refs/heads/{} [rejected] ({})",
+                    remote, branch, reason
+                )
+                .into());
+            }
+            repository.remote_branches.insert(branch, head);
+        }
+        repository.pushed.extend(
+            refspecs
+                .iter()
+                .map(|refspec| format!("{} {}", remote, refspec)),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_head_sha() {
+        let git = InMemoryGit::new();
+        assert!(git.head_sha().is_err());
+
+        let first = git.commit_files("chore: initial commit", &["api/package.json"]);
+        assert_eq!(git.head_sha().unwrap(), first);
+        let second = git.commit_files("feat(api): add orders", &["api/index.js"]);
+        assert_eq!(git.head_sha().unwrap(), second);
+
+        git.checkout(&first);
+        assert_eq!(git.head_sha().unwrap(), first);
+    }
+
+    #[test]
+    fn test_checkout_branch() {
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &["api/package.json"]);
+        git.branch("main");
+        git.branch("production-release");
+        git.commit_files("chore(release): bump packages", &["api/package.json"]);
+        git.checkout("main");
+        let main = git.commit_files("feat(api): add orders", &["api/index.js"]);
+
+        // The previous release commit is dropped from the reset branch
+        git.checkout_branch("production-release", "main").unwrap();
+        assert_eq!(git.current_branch().as_deref(), Some("production-release"));
+        assert_eq!(git.head_sha().unwrap(), main);
+        assert_eq!(
+            git.commit_messages(),
+            vec!["chore: initial commit", "feat(api): add orders"]
+        );
+        assert!(git
+            .checkout_branch("production-release", "develop")
+            .is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use std::path::{Path, PathBuf};
+
+use git2::{Commit, DiffOptions, Repository, Sort, StatusOptions, Tree};
+
+use super::{CliGit, GitBackend, GitResult};
+use crate::{log_warning, Signing};
+
+/// Reads refs, history and trees in-process with libgit2. Writes still go through
+/// the `git` executable so that hooks, signing and user configuration apply.
+pub struct LibGit {
+    repository: Repository,
+    cli: CliGit,
+}
+
+impl LibGit {
+    /// Opens the repository of the directory the `cli` backend runs in.
+    pub fn open(dir: impl AsRef<Path>, cli: CliGit) -> GitResult<Self> {
+        let repository = Repository::discover(dir.as_ref())?;
+        if repository.is_bare() {
+            return Err("bare repositories are not supported".into());
+        }
+        Ok(LibGit { repository, cli })
+    }
+
+    fn workdir(&self) -> PathBuf {
+        let workdir = self.repository.workdir().unwrap_or(Path::new("."));
+        workdir.canonicalize().unwrap_or(workdir.to_path_buf())
+    }
+
+    /// Converts manifest paths (`./api`, `api/`, absolute paths) to pathspecs
+    /// relative to the repository root. `None` means the whole repository.
+    fn pathspecs(&self, paths: &[&str]) -> Option<Vec<String>> {
+        let workdir = self.workdir();
+        let mut pathspecs = Vec::new();
+        for path in paths {
+            let path = Path::new(path);
+            let relative = if path.is_absolute() {
+                let path = path.canonicalize().unwrap_or(path.to_path_buf());
+                path.strip_prefix(&workdir)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(path)
+            } else {
+                path.to_path_buf()
+            };
+            let relative = relative.to_string_lossy();
+            let relative = relative.trim_start_matches("./").trim_end_matches('/');
+            if relative.is_empty() || relative == "." {
+                return None;
+            }
+            pathspecs.push(relative.to_string());
+        }
+        if pathspecs.is_empty() {
+            None
+        } else {
+            Some(pathspecs)
+        }
+    }
+
+    fn resolve(&self, revision: &str) -> GitResult<Commit<'_>> {
+        Ok(self
+            .repository
+            .revparse_single(revision)?
+            .peel_to_commit()?)
+    }
+
+    fn changed_files(
+        &self,
+        old_tree: Option<&Tree>,
+        new_tree: &Tree,
+        pathspecs: &Option<Vec<String>>,
+    ) -> GitResult<Vec<String>> {
+        let mut options = DiffOptions::new();
+        for pathspec in pathspecs.iter().flatten() {
+            options.pathspec(pathspec);
+        }
+        let diff =
+            self.repository
+                .diff_tree_to_tree(old_tree, Some(new_tree), Some(&mut options))?;
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Mirrors `git log -- <paths>`: a commit is listed when it changes the paths
+    /// compared to its parent, or to every parent for merges.
+    fn touches(&self, commit: &Commit, pathspecs: &Option<Vec<String>>) -> GitResult<bool> {
+        if pathspecs.is_none() {
+            return Ok(true);
+        }
+        let tree = commit.tree()?;
+        if commit.parent_count() == 0 {
+            return Ok(!self.changed_files(None, &tree, pathspecs)?.is_empty());
+        }
+        for parent in commit.parents() {
+            if self
+                .changed_files(Some(&parent.tree()?), &tree, pathspecs)?
+                .is_empty()
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl GitBackend for LibGit {
+    fn head_sha(&self) -> GitResult<String> {
+        Ok(self.repository.head()?.peel_to_commit()?.id().to_string())
+    }
+
+    fn list_tags(&self, pattern: &str) -> GitResult<Vec<String>> {
+        let tags = self.repository.tag_names(Some(pattern))?;
+        Ok(tags.iter().flatten().map(str::to_string).collect())
+    }
+
+    fn merged_tags(&self, pattern: &str, revision: &str) -> GitResult<Vec<String>> {
+        let target = self.resolve(revision)?.id();
+        let mut merged = Vec::new();
+        for tag in self.list_tags(pattern)? {
+            let tagged = self.resolve(&format!("refs/tags/{}", tag))?.id();
+            if tagged == target || self.repository.graph_descendant_of(target, tagged)? {
+                merged.push(tag);
+            }
+        }
+        Ok(merged)
+    }
+
+    fn log(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let pathspecs = self.pathspecs(paths);
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(self.resolve(to)?.id())?;
+        revwalk.hide(self.resolve(from)?.id())?;
+
+        let mut lines = Vec::new();
+        for oid in revwalk {
+            let commit = self.repository.find_commit(oid?)?;
+            if self.touches(&commit, &pathspecs)? {
+                let short_id = commit.as_object().short_id()?;
+                lines.push(format!(
+                    "{} {}",
+                    short_id.as_str().unwrap_or_default(),
+                    commit.summary().unwrap_or_default()
+                ));
+            }
+        }
+        Ok(lines)
+    }
+
+    fn diff_paths(&self, from: &str, to: &str, paths: &[&str]) -> GitResult<Vec<String>> {
+        let old_tree = self.resolve(from)?.tree()?;
+        let new_tree = self.resolve(to)?.tree()?;
+        self.changed_files(Some(&old_tree), &new_tree, &self.pathspecs(paths))
+    }
+
+    fn commit_paths(&self, commit: &str) -> GitResult<Vec<String>> {
+        let commit = self.resolve(commit)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        self.changed_files(parent_tree.as_ref(), &commit.tree()?, &None)
+    }
+
+    fn status(&self) -> GitResult<Vec<String>> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let statuses = self.repository.statuses(Some(&mut options))?;
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| entry.path().map(str::to_string))
+            .collect())
+    }
+
+    fn first_commit(&self, paths: &[&str]) -> GitResult<Option<String>> {
+        let pathspecs = self.pathspecs(paths);
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        revwalk.push_head()?;
+        for oid in revwalk {
+            let commit = self.repository.find_commit(oid?)?;
+            if self.touches(&commit, &pathspecs)? {
+                return Ok(Some(commit.id().to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    fn is_shallow(&self) -> GitResult<bool> {
+        Ok(self.repository.is_shallow())
+    }
+
+    fn fetch_tags(&self, remote: &str) -> GitResult<()> {
+        self.cli.fetch_tags(remote)
+    }
+
+    fn fetch_history(&self, remote: &str, depth: Option<u32>) -> GitResult<()> {
+        self.cli.fetch_history(remote, depth)
+    }
+
+    fn create_tag(&self, tag: &str, message: Option<&str>) -> GitResult<()> {
+        self.cli.create_tag(tag, message)
+    }
+
+    fn checkout_branch(&self, branch: &str, start_point: &str) -> GitResult<()> {
+        self.cli.checkout_branch(branch, start_point)
+    }
+
+    fn add(&self, paths: &[&str]) -> GitResult<()> {
+        self.cli.add(paths)
+    }
+
+    fn commit(&self, message: &str) -> GitResult<()> {
+        self.cli.commit(message)
+    }
+
+    fn remote_sha(&self, remote: &str, branch: &str) -> GitResult<String> {
+        self.cli.remote_sha(remote, branch)
+    }
+
+    fn push(&self, remote: &str, refspecs: &[&str], lease: Option<&str>) -> GitResult<()> {
+        self.cli.push(remote, refspecs, lease)
+    }
+}
+
+/// Opens the repository in-process, falling back to the `git` executable when
+/// libgit2 cannot read it or when `use_cli` is set.
+pub fn open_git_backend(dir: &str, use_cli: bool, signing: &Signing) -> Box<dyn GitBackend> {
+    let cli = || CliGit::new(dir).with_signing(signing.clone());
+    if use_cli {
+        return Box::new(cli());
+    }
+    match LibGit::open(dir, cli()) {
+        Ok(git) => Box::new(git),
+        Err(e) => {
+            log_warning(&format!(
+                "Could not open the repository in-process ({}), using the git executable",
+                e
+            ));
+            Box::new(cli())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process::Command};
+    use tempfile::tempdir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(["-c", "commit.gpgsign=false", "-c", "tag.gpgsign=false"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn commit_file(dir: &Path, file: &str, message: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, message).unwrap();
+        git(dir, &["add", file]);
+        git(dir, &["commit", "-q", "-m", message]);
+    }
+
+    #[test]
+    fn test_matches_git_executable() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        commit_file(root, "api/package.json", "chore: initial commit");
+        git(root, &["tag", "api-v1.0.0"]);
+        git(root, &["tag", "web-v1.0.0"]);
+        commit_file(root, "api/src/index.js", "feat(api): add orders");
+        commit_file(root, "web/index.js", "fix(web): fix layout");
+        commit_file(root, "api/src/index.js", "fix(api): fix totals");
+
+        let library = LibGit::open(root, CliGit::new(root)).unwrap();
+        let cli = CliGit::new(root);
+
+        assert_eq!(library.head_sha().unwrap(), cli.head_sha().unwrap());
+        assert_eq!(
+            library.list_tags("api-v*").unwrap(),
+            cli.list_tags("api-v*").unwrap()
+        );
+        for paths in [vec![], vec!["api"], vec!["./api/"], vec!["web"], vec!["."]] {
+            assert_eq!(
+                library.log("api-v1.0.0", "HEAD", &paths).unwrap(),
+                cli.log("api-v1.0.0", "HEAD", &paths).unwrap()
+            );
+            assert_eq!(
+                library.diff_paths("api-v1.0.0", "HEAD", &paths).unwrap(),
+                cli.diff_paths("api-v1.0.0", "HEAD", &paths).unwrap()
+            );
+        }
+
+        git(root, &["checkout", "-q", "-b", "maintenance", "api-v1.0.0"]);
+        commit_file(root, "api/src/index.js", "fix(api): backport fix");
+        git(root, &["tag", "api-v1.0.1"]);
+        git(root, &["checkout", "-q", "-"]);
+        assert_eq!(
+            library.merged_tags("api-v*", "HEAD").unwrap(),
+            cli.merged_tags("api-v*", "HEAD").unwrap()
+        );
+        assert_eq!(
+            library.merged_tags("api-v*", "HEAD").unwrap(),
+            vec!["api-v1.0.0"]
+        );
+
+        for commit in ["HEAD", "HEAD~2", "api-v1.0.0", "maintenance"] {
+            assert_eq!(
+                library.commit_paths(commit).unwrap(),
+                cli.commit_paths(commit).unwrap()
+            );
+        }
+
+        for paths in [vec!["api"], vec!["web"], vec!["docs"]] {
+            assert_eq!(
+                library.first_commit(&paths).unwrap(),
+                cli.first_commit(&paths).unwrap()
+            );
+        }
+
+        assert!(library.status().unwrap().is_empty());
+        fs::write(root.join("api/package.json"), "{}").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+        assert_eq!(library.status().unwrap(), cli.status().unwrap());
+        assert_eq!(
+            library.status().unwrap(),
+            vec!["api/package.json", "notes.txt"]
+        );
+
+        let absolute = root.join("api");
+        assert_eq!(
+            library
+                .diff_paths("api-v1.0.0", "HEAD", &[absolute.to_str().unwrap()])
+                .unwrap(),
+            vec!["api/src/index.js"]
+        );
+        assert!(library.log("missing-v1.0.0", "HEAD", &[]).is_err());
+    }
+}
@@ -1,2 +1,13 @@
-pub use self::commands::{format_commit_message, get_latest_tag};
+pub use self::backend::{CliGit, GitBackend, GitResult};
+pub use self::commands::{
+    ensure_release_history, format_commit_message, format_release_commit_message,
+    get_changed_files, get_latest_tag, get_package_log, warn_unreachable_tags,
+};
+#[cfg(test)]
+pub use self::fake::InMemoryGit;
+pub use self::library::open_git_backend;
+mod backend;
 mod commands;
+#[cfg(test)]
+mod fake;
+mod library;
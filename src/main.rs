@@ -6,7 +6,7 @@ use std::{
     fs::{self, OpenOptions},
 };
 mod cli;
-use cli::Args;
+use cli::{Args, Command};
 mod logging;
 use logging::*;
 mod git;
@@ -19,6 +19,8 @@ mod changelog;
 use changelog::*;
 mod utils;
 use utils::*;
+mod forge;
+use forge::*;
 
 fn has_dependency_changes(package: &Package, changed_packages: &HashMap<String, String>) -> bool {
     package
@@ -47,102 +49,272 @@ fn get_higher_semver(current_semver: Semver, new_semver: Semver) -> Semver {
     }
 }
 
-fn determine_semver_target(name: &str, version: &str, environment: &str) -> Semver {
-    let last_tag = get_latest_tag(name, version, environment).unwrap();
-    let interval = format!("{}..HEAD", last_tag);
-    let git_log_output = std::process::Command::new("git")
-        .args(&["log", &interval, "--oneline"])
-        .output()
-        .expect("Failed to execute git log command");
+/// Returns the revision the changes of a package are computed from: its latest
+/// tag, or for a first release its `bootstrapSha` or the commit that added it.
+fn get_release_base(
+    git: &dyn GitBackend,
+    package: &Package,
+    tag_format: &TagFormat,
+    environment: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(tag) = get_latest_tag(git, tag_format, environment)? {
+        return Ok(tag);
+    }
+    if let Some(bootstrap_sha) = &package.bootstrap_sha {
+        log_info(&format!(
+            "No release tag found - Using bootstrapSha {}",
+            bootstrap_sha
+        ));
+        return Ok(bootstrap_sha.clone());
+    }
+    let first_commit = git
+        .first_commit(&[&package.path])?
+        .ok_or_else(|| format!("No commit found for {}", package.path))?;
+    log_info(&format!(
+        "No release tag found - Using the commit that added {} ({})",
+        package.path, first_commit
+    ));
+    Ok(first_commit)
+}
 
-    let git_log_result = String::from_utf8_lossy(&git_log_output.stdout);
+/// Tag formats of the packages whose history starts at their release tags, so
+/// leaving out the ones bootstrapped from a commit.
+fn get_release_tag_formats(
+    git: &dyn GitBackend,
+    manifest: &Manifest,
+) -> Result<Vec<(String, TagFormat)>, Box<dyn std::error::Error>> {
+    manifest
+        .packages
+        .iter()
+        .filter(|package| package.bootstrap_sha.is_none())
+        .map(|package| {
+            let (name, _) = get_version_and_name(git, package)?;
+            let tag_format = get_tag_format(package, &name)?;
+            Ok((name, tag_format))
+        })
+        .collect()
+}
 
-    let mut semver_target = Semver::Patch;
-    for line in git_log_result.lines() {
-        if line.contains("feat(") {
-            semver_target = get_higher_semver(semver_target, Semver::Minor);
-        }
-        if line.contains("!feat(") || line.contains("!fix(") {
-            return Semver::Major;
-        }
+/// Files written by releaser for the CI, which do not make the tree dirty.
+const OUTPUT_FILES: [&str; 2] = ["tags_to_create.txt", "pull_request_content.md"];
+
+/// Fails when the working tree has uncommitted changes, which could end up
+/// mixed with the version bumps.
+fn check_clean_tree(git: &dyn GitBackend) -> Result<(), Box<dyn std::error::Error>> {
+    let dirty_files: Vec<String> = git
+        .status()?
+        .into_iter()
+        .filter(|path| !OUTPUT_FILES.contains(&path.as_str()))
+        .collect();
+    if dirty_files.is_empty() {
+        return Ok(());
     }
-    semver_target
+    Err(format!(
+        "The working tree has uncommitted changes ({}). Commit or stash them, or pass --allow-dirty",
+        dirty_files.join(", ")
+    )
+    .into())
 }
 
 struct DryRunConfig {
     is_dry_run: bool,
 }
 
+/// Annotation of a release tag: the tag name, followed by the changelog section
+/// of the version when there is one.
+fn get_tag_message(package: &Package, tag: &str, version: &str) -> String {
+    let changelog = fs::read_to_string(package.path.clone() + "/CHANGELOG.md").unwrap_or_default();
+    match get_changelog_section(&changelog, version) {
+        Some(section) => format!("{}\n\n{}", tag, section),
+        None => tag.to_string(),
+    }
+}
+
+/// Version of the latest release of a package without a version file, read from
+/// the newest section of its changelog.
+fn get_changelog_version(package: &Package) -> Option<String> {
+    let changelog = fs::read_to_string(package.path.clone() + "/CHANGELOG.md").ok()?;
+    get_latest_changelog_version(&changelog)
+}
+
 fn process_tag_creation(
-    name: &str,
+    git: &dyn GitBackend,
+    package: &Package,
+    tag_format: &TagFormat,
     version: &str,
+    lightweight_tags: bool,
     dry_run_config: &DryRunConfig,
     tags_to_create: &mut Vec<String>,
-) {
-    let tag = format!("{}-v{}", name, version);
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tag = tag_format.tag(version);
     if !dry_run_config.is_dry_run {
         // Check if the tag already exists
-        let tag_exists = std::process::Command::new("git")
-            .args(&["tag", "-l", &tag])
-            .output()
-            .map(|output| !output.stdout.is_empty())
+        let tag_exists = git
+            .list_tags(&tag)
+            .map(|tags| !tags.is_empty())
             .unwrap_or(false);
 
         if tag_exists {
             println!("Tag {} already exists. Skipping tag creation.", tag);
         } else {
+            let message = (!lightweight_tags).then(|| get_tag_message(package, &tag, version));
+            git.create_tag(&tag, message.as_deref())
+                .map_err(|e| format!("Failed to create tag {}: {}", tag, e))?;
             tags_to_create.push(tag.clone());
-            let tag_result = std::process::Command::new("git")
-                .args(&["tag", "-a", &tag, "-m", &tag])
-                .output();
-
-            match tag_result {
-                Ok(output) => {
-                    if output.status.success() {
-                        println!("Created new tag: {}", tag);
-                    } else {
-                        let error = String::from_utf8_lossy(&output.stderr);
-                        eprintln!("Failed to create tag: {}. Error: {}", tag, error);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error executing git tag command: {}", e);
-                }
-            }
+            log_success(&format!("Created new tag: {}", tag));
         }
-
-        log_success(&format!("Created new tag: {}", tag));
     } else {
         log_info(&format!("Would create tag: {}", tag));
     }
+    Ok(())
+}
+
+/// Tags the current version of a package that has never been released, so that
+/// its next release only covers the changes made from now on.
+fn bootstrap_package(
+    git: &dyn GitBackend,
+    package: &Package,
+    lightweight_tags: bool,
+    dry_run_config: &DryRunConfig,
+    tags_to_create: &mut Vec<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (name, version) = get_version_and_name(git, package)?;
+    if !package.package_type.has_version_file() {
+        log_info(&format!("{} has no version file - Skipping", name));
+        return Ok(());
+    }
+
+    let tag_format = get_tag_format(package, &name)?;
+    if let Some(tag) = get_latest_tag(git, &tag_format, "staging")? {
+        log_info(&format!(
+            "{} was already released as {} - Skipping",
+            name, tag
+        ));
+        return Ok(());
+    }
+    process_tag_creation(
+        git,
+        package,
+        &tag_format,
+        &version,
+        lightweight_tags,
+        dry_run_config,
+        tags_to_create,
+    )
 }
 
+/// Commits the files written by releaser, and only those.
 fn commit_changes(
+    git: &dyn GitBackend,
     dry_run_config: &DryRunConfig,
-    name_to_version: &HashMap<String, String>,
+    commit_message: &CommitMessage,
+    changed_packages: &HashMap<String, String>,
+    updated_files: &[String],
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if !dry_run_config.is_dry_run {
-        std::process::Command::new("git")
-            .args(&["add", "."])
-            .output()
-            .expect("Failed to execute git add command");
-        let mut commit_message = String::new();
-        commit_message.push_str("chore(release): bump packages");
-        for (name, version) in name_to_version {
-            commit_message.push_str(&format!("- {}: {}", name, version));
-        }
+    if changed_packages.is_empty() {
+        log_info("No package to release - Nothing to commit");
+        return Ok(());
+    }
+    let commit_message = format_release_commit_message(commit_message, changed_packages);
+    if dry_run_config.is_dry_run {
+        log_info(&format!(
+            "Would create git commit with version bumps:\n{}",
+            commit_message
+        ));
+        return Ok(());
+    }
 
-        std::process::Command::new("git")
-            .args(&["commit", "-m", &commit_message])
-            .output()
-            .expect("Failed to execute git commit command");
-        log_success("Created new commit with version bumps");
-    } else {
-        log_info("Would create git commit with version bumps");
+    let mut updated_files: Vec<&str> = updated_files.iter().map(String::as_str).collect();
+    updated_files.sort();
+    updated_files.dedup();
+    git.add(&updated_files)?;
+    git.commit(&commit_message)?;
+    log_success(&format!(
+        "Created new commit {} with version bumps",
+        git.head_sha()?
+    ));
+    Ok(())
+}
+
+/// Pushes the release branch, or in tag mode the tags created by this run.
+fn push_release(
+    git: &dyn GitBackend,
+    remote: &str,
+    refspecs: &[String],
+    lease: Option<&str>,
+    dry_run_config: &DryRunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if refspecs.is_empty() {
+        log_info("Nothing to push");
+        return Ok(());
+    }
+    if dry_run_config.is_dry_run {
+        log_info(&format!("Would push {} to {}", refspecs.join(", "), remote));
+        return Ok(());
     }
+
+    let refspecs: Vec<&str> = refspecs.iter().map(String::as_str).collect();
+    git.push(remote, &refspecs, lease)
+        .map_err(|e| format!("Failed to push to {}: {}", remote, e))?;
+    log_success(&format!("Pushed {} to {}", refspecs.join(", "), remote));
     Ok(())
 }
 
+/// Creates or resets the release branch from the base branch, so that every run
+/// releases the latest base. Returns the sha of the branch on the remote, which
+/// the release branch may overwrite as long as nobody pushed to it meanwhile.
+fn prepare_release_branch(
+    git: &dyn GitBackend,
+    remote: &str,
+    branch: &str,
+    base: &str,
+    dry_run_config: &DryRunConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if dry_run_config.is_dry_run {
+        log_info(&format!("Would reset branch {} from {}", branch, base));
+        return Ok(String::new());
+    }
+    let lease = git
+        .remote_sha(remote, branch)
+        .map_err(|e| format!("Failed to read branch {} of {}: {}", branch, remote, e))?;
+    git.checkout_branch(branch, base)
+        .map_err(|e| format!("Failed to reset branch {} from {}: {}", branch, base, e))?;
+    log_success(&format!("Reset branch {} from {}", branch, base));
+    Ok(lease)
+}
+
+fn open_release_pull_request(
+    forge: Option<&GitHub>,
+    branch: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(forge) = forge else {
+        log_info(&format!(
+            "Would open or update the pull request of {} into {}",
+            branch, base
+        ));
+        return Ok(());
+    };
+    let (pull_request, created) = forge.open_or_update_pull_request(branch, base, title, body)?;
+    log_success(&format!(
+        "{} pull request #{}: {}",
+        if created { "Opened" } else { "Updated" },
+        pull_request.number,
+        pull_request.html_url
+    ));
+    Ok(())
+}
+
+fn get_pull_request_title(environment: &str) -> String {
+    let mut chars = environment.chars();
+    match chars.next() {
+        Some(first) => format!("{}{} Release", first.to_uppercase(), chars.as_str()),
+        None => "Release".to_string(),
+    }
+}
+
 fn write_tags_file(tags_to_create: &[String]) -> Result<(), Box<dyn std::error::Error>> {
     let tags_file_path = "tags_to_create.txt";
     let mut file = OpenOptions::new()
@@ -161,15 +333,17 @@ fn write_tags_file(tags_to_create: &[String]) -> Result<(), Box<dyn std::error::
 }
 
 fn process_package_changes(
+    git: &dyn GitBackend,
     package: &Package,
+    manifest: &Manifest,
     environment: &str,
     dry_run_config: &DryRunConfig,
     changed_packages: &mut HashMap<String, String>,
-    name_to_version: &mut HashMap<String, String>,
     pull_request_content: &mut String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let (name, version) = get_version_and_name(&package.path).unwrap();
-    let last_tag = get_latest_tag(&name, &version, &environment).unwrap();
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let (name, version) = get_version_and_name(git, package)?;
+    let tag_format = get_tag_format(package, &name)?;
+    let rules = PathRules::new(package, &name, manifest)?;
 
     println!(
         "{} {} ({})",
@@ -178,35 +352,17 @@ fn process_package_changes(
         package.path.bright_black()
     );
     println!("   Current version: {}", version.bright_yellow());
+    warn_unreachable_tags(git, &tag_format, environment)?;
+    let last_tag = get_release_base(git, package, &tag_format, environment)?;
     println!("   Latest tag: {}", last_tag.bright_yellow());
 
-    let output = std::process::Command::new("git")
-        .args(&[
-            "diff",
-            "--name-only",
-            &last_tag,
-            "HEAD",
-            "--",
-            &package.path,
-        ])
-        .output()
-        .expect("Failed to execute git diff command");
-
-    let git_diff_result = String::from_utf8_lossy(&output.stdout);
-
-    if git_diff_result.is_empty() {
+    let changed_files = get_changed_files(git, &rules, &last_tag, "HEAD")?;
+    if changed_files.is_empty() {
         log_info("No changes detected - Skipping");
-
-        ()
+        return Ok(Vec::new());
     }
 
-    let interval = last_tag + "..HEAD";
-    let git_log_output = std::process::Command::new("git")
-        .args(&["log", &interval, "--oneline", "--", &package.path])
-        .output()
-        .expect("Failed to execute git log command");
-
-    let git_log_result = String::from_utf8_lossy(&git_log_output.stdout);
+    let git_log_result = get_package_log(git, &rules, &last_tag, "HEAD")?;
 
     let mut changelog = Changelog {
         features: String::new(),
@@ -216,51 +372,52 @@ fn process_package_changes(
     };
 
     let mut semver_target: Semver = Semver::Patch;
-    for line in git_log_result.lines() {
+    for line in &git_log_result {
         let commit_message = format_commit_message(line);
         if line.contains("feat(") {
             changelog.features.push_str(&commit_message);
-            changelog.features.push_str("\n");
+            changelog.features.push('\n');
             semver_target = get_higher_semver(semver_target, Semver::Minor);
         }
         if line.contains("fix(") {
             changelog.fixes.push_str(&commit_message);
-            changelog.fixes.push_str("\n");
+            changelog.fixes.push('\n');
             semver_target = get_higher_semver(semver_target, Semver::Patch);
         }
         if line.contains("perf(") {
             changelog.perf.push_str(&commit_message);
-            changelog.perf.push_str("\n");
+            changelog.perf.push('\n');
             semver_target = get_higher_semver(semver_target, Semver::Patch);
         }
         if line.contains("!feat(") || line.contains("!fix(") {
             changelog.breaking.push_str(&commit_message);
-            changelog.breaking.push_str("\n");
+            changelog.breaking.push('\n');
             semver_target = get_higher_semver(semver_target, Semver::Major);
         }
     }
 
-    let new_version = increase_version(&version, semver_target, &environment);
+    let new_version = increase_version(&version, semver_target, environment);
+    if let Some(warning) = get_version_warning(package, &name, &new_version) {
+        log_warning(&warning);
+    }
     let new_changelog = get_new_changelog(&name, &new_version, changelog);
+    let mut updated_files = Vec::new();
 
-    if new_changelog.is_ok() {
-        let changelog_body = new_changelog.unwrap();
-
+    if let Ok(changelog_body) = new_changelog {
         let current_changelog = fs::read_to_string(package.path.clone() + "/CHANGELOG.md").ok();
         let updated_changelog = update_changelog(
             current_changelog.as_deref(),
             &name,
             &changelog_body,
-            &dry_run_config,
+            dry_run_config,
         )
         .expect("Changelog update failed");
 
         if !dry_run_config.is_dry_run {
-            fs::write(
-                package.path.to_string() + "/CHANGELOG.md",
-                updated_changelog,
-            )
-            .expect("Failed to write updated CHANGELOG.md");
+            let changelog_path = package.path.to_string() + "/CHANGELOG.md";
+            fs::write(&changelog_path, updated_changelog)
+                .expect("Failed to write updated CHANGELOG.md");
+            updated_files.push(changelog_path);
         }
 
         let filtered_changelog_body: String = changelog_body
@@ -273,7 +430,7 @@ fn process_package_changes(
         pull_request_content.push_str(format!("{}\n\n", filtered_changelog_body).as_str());
     }
 
-    update_package(&package.path, &new_version, &dry_run_config).unwrap();
+    updated_files.extend(update_package(package, &new_version, dry_run_config)?);
 
     log_success(&format!(
         "Updated {} from {} to {}",
@@ -283,50 +440,58 @@ fn process_package_changes(
     ));
 
     if !package.extra_files.is_empty() {
-        increase_extra_files_version(&package.extra_files, &new_version, &dry_run_config);
+        updated_files.extend(increase_extra_files_version(
+            &package.path,
+            &package.extra_files,
+            &new_version,
+            dry_run_config,
+        )?);
     } else {
         println!("No extraFiles found for package {}", name);
     }
 
     changed_packages.insert(name.clone(), new_version.clone());
-    name_to_version.insert(name.to_string(), new_version.to_string());
-    Ok(())
+    Ok(updated_files)
 }
 
 fn process_dependencies(
+    git: &dyn GitBackend,
     packages: &[Package],
     environment: &str,
     dry_run_config: &DryRunConfig,
     changed_packages: &mut HashMap<String, String>,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut updated_files = Vec::new();
     for package in packages {
-        let (name, version) = get_version_and_name(&package.path).unwrap();
-        let mut should_update = changed_packages.contains_key(&name);
+        let (name, version) = get_version_and_name(git, package)?;
 
-        if !should_update {
-            should_update = has_dependency_changes(package, &changed_packages);
+        // Packages with their own changes were already bumped
+        if changed_packages.contains_key(&name) {
+            continue;
         }
 
-        if should_update {
-            // Determine new version (consider both direct changes and dependency updates)
-            let semver_target = if changed_packages.contains_key(&name) {
-                determine_semver_target(&name, &version, &environment)
-            } else {
-                Semver::Patch // For dependency updates, use patch version
-            };
-
-            let new_version = increase_version(&version, semver_target, &environment);
+        if has_dependency_changes(package, changed_packages) {
+            // For dependency updates, use patch version
+            let new_version = increase_version(&version, Semver::Patch, environment);
+            if let Some(warning) = get_version_warning(package, &name, &new_version) {
+                log_warning(&warning);
+            }
 
-            update_package(&package.path, &new_version, &dry_run_config).unwrap();
+            updated_files.extend(update_package(package, &new_version, dry_run_config)?);
 
             if !package.extra_files.is_empty() {
-                increase_extra_files_version(&package.extra_files, &new_version, &dry_run_config);
+                updated_files.extend(increase_extra_files_version(
+                    &package.path,
+                    &package.extra_files,
+                    &new_version,
+                    dry_run_config,
+                )?);
             }
 
             changed_packages.insert(name.clone(), new_version.clone());
         }
     }
-    Ok(())
+    Ok(updated_files)
 }
 
 fn main() {
@@ -344,34 +509,174 @@ fn main() {
     log_info(&format!("Environment: {}", args.environment.bright_cyan()));
 
     let manifest: Manifest = get_manifest().unwrap();
+    let signing = Signing {
+        commits: manifest.signing.commits || args.sign,
+        tags: manifest.signing.tags || args.sign,
+        key: args.signing_key.clone().or(manifest.signing.key.clone()),
+        format: args
+            .signing_format
+            .clone()
+            .or(manifest.signing.format.clone()),
+    };
+    let mut git_backend = open_git_backend(".", args.git_cli, &signing);
     let mut changed_packages = HashMap::new();
     let mut pull_request_content = String::new();
-    let mut name_to_version = HashMap::new();
     let mut tags_to_create = Vec::new();
+    let mut updated_files = Vec::new();
+
+    let lightweight_tags = manifest.lightweight_tags || args.lightweight_tags;
+    let remote = args
+        .remote
+        .clone()
+        .or(manifest.remote.clone())
+        .unwrap_or("origin".to_string());
+    let tag_formats =
+        get_release_tag_formats(git_backend.as_ref(), &manifest).unwrap_or_else(|e| {
+            eprintln!("Error reading the packages: {}", e);
+            std::process::exit(1);
+        });
+    match ensure_release_history(git_backend.as_ref(), &tag_formats, &remote, args.fetch) {
+        // Reopen the repository to read the fetched history
+        Ok(true) => git_backend = open_git_backend(".", args.git_cli, &signing),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    let git = git_backend.as_ref();
+
+    let is_bootstrap = matches!(args.command, Some(Command::Bootstrap));
+    let is_tag = args.tag || is_bootstrap;
+    let push = args.push || matches!(args.command, Some(Command::ReleasePr { .. }));
+
+    if !is_tag && !args.allow_dirty {
+        if let Err(e) = check_clean_tree(git) {
+            if dry_run_config.is_dry_run {
+                log_warning(&e.to_string());
+            } else {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // API client, branch, base, title and push lease of the release pull request
+    let mut release_pr = None;
+    if let Some(Command::ReleasePr {
+        branch,
+        base,
+        title,
+        repository,
+    }) = &args.command
+    {
+        let forge = match GitHub::from_env(repository.as_deref()) {
+            Ok(forge) => Some(forge).filter(|_| !dry_run_config.is_dry_run),
+            Err(e) if dry_run_config.is_dry_run => {
+                log_warning(&e.to_string());
+                None
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let branch = branch
+            .clone()
+            .unwrap_or(format!("{}-release", args.environment));
+        let lease = match prepare_release_branch(git, &remote, &branch, base, &dry_run_config) {
+            Ok(lease) => lease,
+            Err(e) => {
+                eprintln!("Error preparing the release branch: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let title = title
+            .clone()
+            .unwrap_or(get_pull_request_title(&args.environment));
+        release_pr = Some((forge, branch, base.clone(), title, lease));
+    }
 
     log_section("Analyzing Packages");
 
     for package in &manifest.packages {
+        if is_bootstrap {
+            if let Err(e) = bootstrap_package(
+                git,
+                package,
+                lightweight_tags,
+                &dry_run_config,
+                &mut tags_to_create,
+            ) {
+                eprintln!("Error bootstrapping package: {}", e);
+                std::process::exit(1);
+            }
+            continue;
+        }
+
         if args.tag {
-            let (name, version) = get_version_and_name(&package.path).unwrap();
-            process_tag_creation(&name, &version, &dry_run_config, &mut tags_to_create);
+            let (name, version) = match get_version_and_name(git, package) {
+                Ok(name_and_version) => name_and_version,
+                Err(e) => {
+                    eprintln!("Error reading package {}: {}", package.path, e);
+                    std::process::exit(1);
+                }
+            };
+            let tag_format = match get_tag_format(package, &name) {
+                Ok(tag_format) => tag_format,
+                Err(e) => {
+                    eprintln!("Error reading the tag format of {}: {}", name, e);
+                    std::process::exit(1);
+                }
+            };
+            let version = if package.package_type.has_version_file() {
+                version
+            } else {
+                // The release commit added the version to the changelog
+                match get_changelog_version(package) {
+                    Some(released_version) => released_version,
+                    None => {
+                        log_info(&format!(
+                            "No release found in the changelog of {} - Skipping",
+                            name
+                        ));
+                        continue;
+                    }
+                }
+            };
+            if let Err(e) = process_tag_creation(
+                git,
+                package,
+                &tag_format,
+                &version,
+                lightweight_tags,
+                &dry_run_config,
+                &mut tags_to_create,
+            ) {
+                eprintln!("Error creating tag: {}", e);
+                std::process::exit(1);
+            }
             continue;
         }
 
-        if let Err(e) = process_package_changes(
+        match process_package_changes(
+            git,
             package,
+            &manifest,
             &args.environment,
             &dry_run_config,
             &mut changed_packages,
-            &mut name_to_version,
             &mut pull_request_content,
         ) {
-            eprintln!("Error processing package: {}", e);
-            std::process::exit(1);
+            Ok(files) => updated_files.extend(files),
+            Err(e) => {
+                eprintln!("Error processing package: {}", e);
+                std::process::exit(1);
+            }
         }
     }
 
-    if args.tag {
+    if is_tag {
         if let Err(e) = write_tags_file(&tags_to_create) {
             eprintln!("Error writing tags file: {}", e);
             std::process::exit(1);
@@ -382,25 +687,79 @@ fn main() {
             tags_to_create.len(),
             "tags_to_create.txt".bright_cyan()
         ));
+
+        if push {
+            let refspecs: Vec<String> = tags_to_create
+                .iter()
+                .map(|tag| format!("refs/tags/{}", tag))
+                .collect();
+            if let Err(e) = push_release(git, &remote, &refspecs, None, &dry_run_config) {
+                eprintln!("Error pushing tags: {}", e);
+                std::process::exit(1);
+            }
+        }
         return;
     }
 
-    if let Err(e) = process_dependencies(
+    match process_dependencies(
+        git,
         &manifest.packages,
         &args.environment,
         &dry_run_config,
         &mut changed_packages,
     ) {
-        eprintln!("Error processing dependencies: {}", e);
-        std::process::exit(1);
+        Ok(files) => updated_files.extend(files),
+        Err(e) => {
+            eprintln!("Error processing dependencies: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    match update_linked_versions(git, &manifest.packages, &changed_packages, &dry_run_config) {
+        Ok(files) => updated_files.extend(files),
+        Err(e) => {
+            eprintln!("Error updating linked versions: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    match update_lockfile(git, &manifest.packages, &changed_packages, &dry_run_config) {
+        Ok(lockfile) => updated_files.extend(lockfile),
+        Err(e) => {
+            eprintln!("Error updating lockfile: {}", e);
+            std::process::exit(1);
+        }
     }
 
     log_section("Commit Changes");
-    if let Err(e) = commit_changes(&dry_run_config, &name_to_version) {
+    if let Err(e) = commit_changes(
+        git,
+        &dry_run_config,
+        &manifest.commit_message,
+        &changed_packages,
+        &updated_files,
+    ) {
         eprintln!("Error committing changes: {}", e);
         std::process::exit(1);
     }
 
+    if release_pr.is_some() && changed_packages.is_empty() {
+        log_info("No package to release - Skipping the release pull request");
+        return;
+    }
+
+    if push {
+        // Only the release branch of release-pr is regenerated by every run, other
+        // branches must not lose the commits pushed since they were checked out
+        let lease = release_pr
+            .as_ref()
+            .map(|(_, _, _, _, lease)| lease.as_str());
+        if let Err(e) = push_release(git, &remote, &["HEAD".to_string()], lease, &dry_run_config) {
+            eprintln!("Error pushing the release branch: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     if !dry_run_config.is_dry_run {
         if let Err(e) = fs::write("pull_request_content.md", &pull_request_content) {
             eprintln!("Error writing pull request content: {}", e);
@@ -408,6 +767,16 @@ fn main() {
         }
     }
 
+    if let Some((forge, branch, base, title, _)) = &release_pr {
+        log_section("Release Pull Request");
+        if let Err(e) =
+            open_release_pull_request(forge.as_ref(), branch, base, title, &pull_request_content)
+        {
+            eprintln!("Error opening the release pull request: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     log_section("Summary");
     log_success(&format!(
         "Updated {} packages",
@@ -418,82 +787,413 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    use mockall::{mock, predicate::*};
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    fn node_package(root: &TempDir, name: &str, dependencies: &[&str]) -> Package {
+        let path = root.path().join(name);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(
+            path.join("package.json"),
+            format!("{{\"name\": \"{}\", \"version\": \"1.0.0\"}}\n", name),
+        )
+        .unwrap();
+        serde_json::from_value(serde_json::json!({
+            "path": path.to_str().unwrap(),
+            "dependencies": dependencies,
+        }))
+        .unwrap()
+    }
 
-    mock! {
-        FileSystem {
-            fn read_to_string(&self, path: &str) -> std::io::Result<String>;
-            fn write(&self, path: &str, contents: &str) -> std::io::Result<()>;
+    fn manifest_of(packages: &[Package]) -> Manifest {
+        Manifest {
+            packages: packages.to_vec(),
+            ..Default::default()
         }
     }
 
-    mock! {
-        GitCommand {
-            fn diff(&self, last_tag: &str, path: &str) -> String;
-            fn log(&self, interval: &str) -> String;
+    fn process_all(
+        git: &InMemoryGit,
+        manifest: &Manifest,
+    ) -> (HashMap<String, String>, Vec<String>) {
+        let dry_run_config = DryRunConfig { is_dry_run: false };
+        let packages = &manifest.packages;
+        let mut changed_packages = HashMap::new();
+        let mut pull_request_content = String::new();
+        let mut updated_files = Vec::new();
+        for package in packages {
+            updated_files.extend(
+                process_package_changes(
+                    git,
+                    package,
+                    manifest,
+                    "production",
+                    &dry_run_config,
+                    &mut changed_packages,
+                    &mut pull_request_content,
+                )
+                .unwrap(),
+            );
         }
+        updated_files.extend(
+            process_dependencies(
+                git,
+                packages,
+                "production",
+                &dry_run_config,
+                &mut changed_packages,
+            )
+            .unwrap(),
+        );
+        (changed_packages, updated_files)
     }
 
-    fn setup_mocks() -> (MockFileSystem, MockGitCommand) {
-        let mut fs = MockFileSystem::new();
-        let git = MockGitCommand::new();
-
-        fs.expect_read_to_string()
-            .with(eq("releaser-manifest.json"))
-            .returning(|_| {
-                Ok(r#"{
-                    "packages": [
-                        {
-                            "path": "package1",
-                            "dependencies": ["package2"]
-                        },
-                        {
-                            "path": "package2",
-                            "dependencies": []
-                        }
-                    ]
-                }"#
-                .to_string())
-            });
+    fn read_version(package: &Package) -> String {
+        let raw = fs::read_to_string(format!("{}/package.json", package.path)).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        json["version"].as_str().unwrap().to_string()
+    }
 
-        (fs, git)
+    #[test]
+    fn test_process_package_changes() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        let web = node_package(&root, "web", &[]);
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &[&api.path, &web.path]);
+        git.tag("api-v1.0.0");
+        git.tag("web-v1.0.0");
+        git.commit_files(
+            "feat(api): add the orders endpoint",
+            &[&format!("{}/index.js", api.path)],
+        );
+
+        let (changed_packages, _) = process_all(&git, &manifest_of(&[api.clone(), web.clone()]));
+
+        assert_eq!(changed_packages.get("api"), Some(&"1.1.0".to_string()));
+        assert!(!changed_packages.contains_key("web"));
+        assert_eq!(read_version(&api), "1.1.0");
+        assert_eq!(read_version(&web), "1.0.0");
+        let changelog = fs::read_to_string(format!("{}/CHANGELOG.md", api.path)).unwrap();
+        assert!(changelog.contains("**api**: add the orders endpoint"));
     }
 
     #[test]
     fn test_dependency_update() {
-        let (mut fs, mut git) = setup_mocks();
-
-        // Mock package1 (no changes)
-        fs.expect_read_to_string()
-            .with(eq("package1/package.json"))
-            .returning(|_| Ok(r#"{"name": "package1", "version": "1.0.0"}"#.to_string()));
-        git.expect_diff()
-            .with(eq("package1-v1.0.0"), eq("package1"))
-            .returning(|_, _| String::new());
-
-        // Mock package2 (with changes)
-        fs.expect_read_to_string()
-            .with(eq("package2/package.json"))
-            .returning(|_| Ok(r#"{"name": "package2", "version": "1.0.0"}"#.to_string()));
-        git.expect_diff()
-            .with(eq("package2-v1.0.0"), eq("package2"))
-            .returning(|_, _| "some_changed_file.js".to_string());
-        git.expect_log()
-            .with(eq("package2-v1.0.0..HEAD"))
-            .returning(|_| "abcdef1 fix(package2): some bugfix".to_string());
-
-        // Expect updates
-        fs.expect_write()
-            .with(
-                eq("package2/package.json"),
-                eq(r#"{"name": "package2", "version": "1.0.1"}"#),
-            )
-            .returning(|_, _| Ok(()));
-        fs.expect_write()
-            .with(
-                eq("package1/package.json"),
-                eq(r#"{"name": "package1", "version": "1.0.1"}"#),
+        let root = tempdir().unwrap();
+        let package1 = node_package(&root, "package1", &["package2"]);
+        let package2 = node_package(&root, "package2", &[]);
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &[&package1.path, &package2.path]);
+        git.tag("package1-v1.0.0");
+        git.tag("package2-v1.0.0");
+        git.commit_files(
+            "fix(package2): some bugfix",
+            &[&format!("{}/some_changed_file.js", package2.path)],
+        );
+
+        let (changed_packages, _) =
+            process_all(&git, &manifest_of(&[package1.clone(), package2.clone()]));
+
+        assert_eq!(changed_packages.get("package2"), Some(&"1.0.1".to_string()));
+        assert_eq!(changed_packages.get("package1"), Some(&"1.0.1".to_string()));
+        assert_eq!(read_version(&package2), "1.0.1");
+        assert_eq!(read_version(&package1), "1.0.1");
+    }
+
+    #[test]
+    fn test_first_release_without_tag() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        let mut web = node_package(&root, "web", &[]);
+
+        let git = InMemoryGit::new();
+        git.commit_files("feat(api): initial version", &[&api.path]);
+        git.commit_files("feat(web): initial version", &[&web.path]);
+        git.commit_files("fix(api): fix totals", &[&format!("{}/index.js", api.path)]);
+        web.bootstrap_sha = Some(git.commit_files(
+            "feat(web): add layout",
+            &[&format!("{}/index.js", web.path)],
+        ));
+        git.commit_files("fix(web): fix layout", &[&format!("{}/index.js", web.path)]);
+
+        let (changed_packages, _) = process_all(&git, &manifest_of(&[api.clone(), web.clone()]));
+
+        // The commits adding the package and before bootstrapSha are not part of the release
+        assert_eq!(changed_packages.get("api"), Some(&"1.0.1".to_string()));
+        assert_eq!(changed_packages.get("web"), Some(&"1.0.1".to_string()));
+        let changelog = fs::read_to_string(format!("{}/CHANGELOG.md", web.path)).unwrap();
+        assert!(changelog.contains("fix layout"));
+        assert!(!changelog.contains("add layout"));
+    }
+
+    #[test]
+    fn test_bootstrap_package() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        let web = node_package(&root, "web", &[]);
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &[&api.path, &web.path]);
+        git.tag("web-v0.9.0");
+
+        let dry_run_config = DryRunConfig { is_dry_run: false };
+        let mut tags_to_create = Vec::new();
+        for package in [&api, &web] {
+            bootstrap_package(&git, package, false, &dry_run_config, &mut tags_to_create).unwrap();
+        }
+
+        assert_eq!(tags_to_create, vec!["api-v1.0.0"]);
+        assert_eq!(git.list_tags("api-v*").unwrap(), vec!["api-v1.0.0"]);
+    }
+
+    #[test]
+    fn test_process_tag_creation() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        fs::write(
+            format!("{}/CHANGELOG.md", api.path),
+            "# api\n## Version 1.1.0\n### Features\n**api**: add orders\n\n## Version 1.0.0\n",
+        )
+        .unwrap();
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: release", &["api/package.json"]);
+        git.tag("api-v1.0.0");
+
+        let dry_run_config = DryRunConfig { is_dry_run: false };
+        let tag_format = TagFormat::new("{name}-v{version}", "api", "api").unwrap();
+        let mut tags_to_create = Vec::new();
+        for (version, lightweight_tags) in [("1.0.0", false), ("1.1.0", false), ("1.2.0", true)] {
+            process_tag_creation(
+                &git,
+                &api,
+                &tag_format,
+                version,
+                lightweight_tags,
+                &dry_run_config,
+                &mut tags_to_create,
             )
-            .returning(|_, _| Ok(()));
+            .unwrap();
+        }
+
+        assert_eq!(tags_to_create, vec!["api-v1.1.0", "api-v1.2.0"]);
+        assert_eq!(
+            git.list_tags("api-v*").unwrap(),
+            vec!["api-v1.0.0", "api-v1.1.0", "api-v1.2.0"]
+        );
+        assert_eq!(
+            git.tag_message("api-v1.1.0").unwrap(),
+            "api-v1.1.0\n\n### Features\n**api**: add orders"
+        );
+        assert!(git.tag_message("api-v1.2.0").is_none());
+    }
+
+    #[test]
+    fn test_prepare_release_branch() {
+        let git = InMemoryGit::new();
+        let initial_commit = git.commit_files("chore: initial commit", &["api/package.json"]);
+        git.branch("main");
+        let previous_release =
+            git.commit_files("chore(release): bump packages", &["api/package.json"]);
+        git.remote_branch("production-release");
+        git.checkout_branch("main", &initial_commit).unwrap();
+
+        let dry_run_config = DryRunConfig { is_dry_run: true };
+        let lease = prepare_release_branch(
+            &git,
+            "origin",
+            "production-release",
+            "main",
+            &dry_run_config,
+        )
+        .unwrap();
+        assert_eq!(lease, "");
+        assert_eq!(git.current_branch().as_deref(), Some("main"));
+
+        // The previous release branch is replaced, unless it changed since the reset
+        let dry_run_config = DryRunConfig { is_dry_run: false };
+        let lease = prepare_release_branch(
+            &git,
+            "origin",
+            "production-release",
+            "main",
+            &dry_run_config,
+        )
+        .unwrap();
+        assert_eq!(lease, previous_release);
+        assert_eq!(git.current_branch().as_deref(), Some("production-release"));
+        git.commit_files("chore(release): bump packages", &["api/package.json"]);
+        let release = vec!["HEAD".to_string()];
+        assert!(
+            push_release(&git, "origin", &release, Some("0000000"), &dry_run_config)
+                .unwrap_err()
+                .to_string()
+                .contains("(stale info)")
+        );
+        push_release(&git, "origin", &release, Some(&lease), &dry_run_config).unwrap();
+        assert_eq!(
+            git.remote_sha("origin", "production-release").unwrap(),
+            git.head_sha().unwrap()
+        );
+
+        assert_eq!(get_pull_request_title("production"), "Production Release");
+        assert!(prepare_release_branch(
+            &git,
+            "origin",
+            "production-release",
+            "develop",
+            &dry_run_config,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_release_history_of_bootstrapped_packages() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        let mut web = node_package(&root, "web", &[]);
+
+        let git = InMemoryGit::new();
+        git.commit_files("chore: initial commit", &[&api.path]);
+        git.tag("api-v1.0.0");
+        let bootstrap_sha = git.commit_files("feat(web): add cart", &[&web.path]);
+        git.tag("web-v0.9.0-legacy");
+        git.commit_files("fix(web): fix cart", &[&web.path]);
+        git.make_shallow(2);
+        web.bootstrap_sha = Some(bootstrap_sha);
+
+        // web-v0.9.0-legacy is out of reach, but web starts at its bootstrap commit
+        let manifest = manifest_of(&[api, web]);
+        let tag_formats = get_release_tag_formats(&git, &manifest).unwrap();
+        assert_eq!(tag_formats.len(), 1);
+        assert_eq!(tag_formats[0].0, "api");
+        let error = ensure_release_history(&git, &tag_formats, "origin", false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("no release of api is reachable"));
+    }
+
+    #[test]
+    fn test_push_release() {
+        let git = InMemoryGit::new();
+        git.commit_files("chore: release", &["api/package.json"]);
+
+        let tags = vec!["refs/tags/api-v1.1.0".to_string()];
+        push_release(
+            &git,
+            "upstream",
+            &tags,
+            None,
+            &DryRunConfig { is_dry_run: true },
+        )
+        .unwrap();
+        push_release(
+            &git,
+            "upstream",
+            &[],
+            None,
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap();
+        assert!(git.pushed().is_empty());
+
+        push_release(
+            &git,
+            "upstream",
+            &tags,
+            None,
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap();
+        assert_eq!(git.pushed(), vec!["upstream refs/tags/api-v1.1.0"]);
+
+        // A commit pushed to the remote branch meanwhile is not overwritten
+        git.branch("main");
+        let base = git.head_sha().unwrap();
+        git.commit_files("fix(api): fix totals", &["api/index.js"]);
+        git.remote_branch("main");
+        git.checkout_branch("main", &base).unwrap();
+        git.commit_files("chore(release): bump packages", &["api/package.json"]);
+        let error = push_release(
+            &git,
+            "upstream",
+            &["HEAD".to_string()],
+            None,
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("upstream rejected refs/heads/main [rejected] (non-fast-forward)"));
+        assert_eq!(git.pushed().len(), 1);
+    }
+
+    #[test]
+    fn test_commit_changes() {
+        let root = tempdir().unwrap();
+        let api = node_package(&root, "api", &[]);
+        let web = node_package(&root, "web", &[]);
+
+        let git = InMemoryGit::new();
+        let initial_commit = git.commit_files("chore: initial commit", &[&api.path, &web.path]);
+        git.tag("api-v1.0.0");
+        git.tag("web-v1.0.0");
+        git.commit_files(
+            "feat(api): add orders",
+            &[&format!("{}/index.js", api.path)],
+        );
+        let (changed_packages, updated_files) =
+            process_all(&git, &manifest_of(&[api.clone(), web.clone()]));
+
+        // Stray files of the working tree stay out of the release commit
+        git.modify(".env");
+        git.modify("pull_request_content.md");
+        assert!(check_clean_tree(&git).is_err());
+
+        commit_changes(
+            &git,
+            &DryRunConfig { is_dry_run: false },
+            &CommitMessage::default(),
+            &changed_packages,
+            &updated_files,
+        )
+        .unwrap();
+
+        let messages = git.commit_messages();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[2],
+            "chore(release): bump packages\n\n- api: 1.1.0\n"
+        );
+        assert_eq!(
+            git.diff_paths("api-v1.0.0", "HEAD", &[]).unwrap(),
+            vec![
+                format!("{}/CHANGELOG.md", api.path),
+                format!("{}/index.js", api.path),
+                format!("{}/package.json", api.path),
+            ]
+        );
+        assert_eq!(
+            git.status().unwrap(),
+            vec![".env", "pull_request_content.md"]
+        );
+        assert!(!git
+            .diff_paths(&initial_commit, "HEAD", &[&web.path])
+            .unwrap()
+            .iter()
+            .any(|path| path.ends_with("package.json")));
+
+        // Nothing to commit when no package changed
+        commit_changes(
+            &git,
+            &DryRunConfig { is_dry_run: false },
+            &CommitMessage::default(),
+            &HashMap::new(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(git.commit_messages().len(), 3);
     }
 }
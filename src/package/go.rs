@@ -0,0 +1,102 @@
+use std::fs;
+
+use super::TagFormat;
+use crate::{get_latest_tag, GitBackend};
+
+pub fn get_tag_prefix(path: &str) -> String {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if path.is_empty() || path == "." {
+        "v".to_string()
+    } else {
+        format!("{}/v", path)
+    }
+}
+
+pub fn get_module_path(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let go_mod_path = path.to_string() + "/go.mod";
+    let go_mod_raw = fs::read_to_string(&go_mod_path)
+        .map_err(|e| format!("Failed to read {}: {}", go_mod_path, e))?;
+
+    go_mod_raw
+        .lines()
+        .find_map(|line| {
+            let module = line.trim().strip_prefix("module")?;
+            if !module.starts_with(char::is_whitespace) {
+                return None;
+            }
+            let module = module.split("//").next().unwrap_or("").trim();
+            Some(module.trim_matches('"').to_string())
+        })
+        .ok_or_else(|| format!("No module directive found in {}", go_mod_path).into())
+}
+
+/// Go modules have no version file: the current version is the one of the latest tag.
+pub fn get_version(
+    git: &dyn GitBackend,
+    tag_format: &TagFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    // Consider beta tags too, as a version file would hold the last released version
+    let latest_tag = get_latest_tag(git, tag_format, "staging")?;
+    Ok(latest_tag
+        .as_deref()
+        .and_then(|tag| tag_format.version(tag))
+        .unwrap_or("0.0.0")
+        .to_string())
+}
+
+/// Returns a warning when the new major version requires a `/vN` module path.
+pub fn get_major_version_warning(module_path: &str, new_version: &str) -> Option<String> {
+    let major = new_version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())?;
+    if major < 2 {
+        return None;
+    }
+
+    let suffix = format!("/v{}", major);
+    if module_path.ends_with(&suffix) {
+        None
+    } else {
+        Some(format!(
+            "{} is bumped to v{} but its module path does not end with {}. Update go.mod and its importers before releasing.",
+            module_path, major, suffix
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_get_tag_prefix() {
+        assert_eq!(get_tag_prefix("services/billing"), "services/billing/v");
+        assert_eq!(get_tag_prefix("./services/billing/"), "services/billing/v");
+        assert_eq!(get_tag_prefix("."), "v");
+    }
+
+    #[test]
+    fn test_get_module_path() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("go.mod"),
+            "// billing service\nmodule github.com/acme/mono/services/billing // main module\n\ngo 1.22\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_module_path(dir.path().to_str().unwrap()).unwrap(),
+            "github.com/acme/mono/services/billing"
+        );
+    }
+
+    #[test]
+    fn test_get_major_version_warning() {
+        assert!(get_major_version_warning("github.com/acme/billing", "1.4.0").is_none());
+        assert!(get_major_version_warning("github.com/acme/billing/v2", "2.0.0").is_none());
+        assert!(get_major_version_warning("github.com/acme/billing", "2.0.0").is_some());
+        assert!(get_major_version_warning("github.com/acme/billing/v2", "3.0.0-beta").is_some());
+    }
+}
@@ -0,0 +1,196 @@
+use regex::Regex;
+use std::{collections::HashMap, fs, ops::Range};
+
+fn chart_path(path: &str) -> String {
+    path.to_string() + "/Chart.yaml"
+}
+
+fn read_chart(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let chart_path = chart_path(path);
+    fs::read_to_string(&chart_path)
+        .map_err(|e| format!("Failed to read {}: {}", chart_path, e).into())
+}
+
+/// Finds the byte range of the value of a `key: value` pair, without its quotes.
+fn find_value(line: &str, key: &str) -> Option<Range<usize>> {
+    let re = Regex::new(r#"^\s*(?:-\s+)?([\w.-]+):[ \t]*(["']?)([^"'#\s]*)"#).unwrap();
+    let captures = re.captures(line)?;
+    if &captures[1] != key {
+        return None;
+    }
+    captures.get(3).map(|value| value.range())
+}
+
+fn find_top_level_value(content: &str, key: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some(range) = find_value(line, key) {
+                return Some(offset + range.start..offset + range.end);
+            }
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn get_top_level_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    find_top_level_value(content, key).map(|range| &content[range])
+}
+
+fn set_top_level_value(content: &str, key: &str, value: &str) -> Option<String> {
+    let range = find_top_level_value(content, key)?;
+    let mut new_content = content.to_string();
+    new_content.replace_range(range, value);
+    Some(new_content)
+}
+
+struct ChartDependency {
+    name: Option<String>,
+    repository: Option<String>,
+    version: Option<Range<usize>>,
+}
+
+fn update_dependencies(content: &str, versions: &HashMap<String, String>) -> String {
+    let mut dependencies: Vec<ChartDependency> = Vec::new();
+    let mut in_dependencies = false;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !line.starts_with(char::is_whitespace)
+            && !trimmed.is_empty()
+            && !trimmed.starts_with('#')
+        {
+            in_dependencies = line.starts_with("dependencies:");
+        } else if in_dependencies && !trimmed.starts_with('#') {
+            if trimmed.starts_with("- ") || trimmed == "-" {
+                dependencies.push(ChartDependency {
+                    name: None,
+                    repository: None,
+                    version: None,
+                });
+            }
+            if let Some(dependency) = dependencies.last_mut() {
+                if let Some(range) = find_value(line, "name") {
+                    dependency.name = Some(line[range].to_string());
+                } else if let Some(range) = find_value(line, "repository") {
+                    dependency.repository = Some(line[range].to_string());
+                } else if let Some(range) = find_value(line, "version") {
+                    dependency.version = Some(offset + range.start..offset + range.end);
+                }
+            }
+        }
+        offset += line.len();
+    }
+
+    let mut new_content = content.to_string();
+    // Replace from the end so the earlier ranges stay valid
+    for dependency in dependencies.iter().rev() {
+        let is_local = dependency
+            .repository
+            .as_deref()
+            .is_some_and(|repository| repository.starts_with("file://"));
+        if !is_local {
+            continue;
+        }
+        if let (Some(name), Some(range)) = (&dependency.name, &dependency.version) {
+            if let Some(version) = versions.get(name) {
+                new_content.replace_range(range.clone(), version);
+            }
+        }
+    }
+    new_content
+}
+
+pub fn get_version_and_name(path: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let chart = read_chart(path)?;
+    let name = get_top_level_value(&chart, "name")
+        .ok_or_else(|| format!("No name found in {}", chart_path(path)))?;
+    let version = get_top_level_value(&chart, "version")
+        .ok_or_else(|| format!("No version found in {}", chart_path(path)))?;
+    Ok((name.to_string(), version.to_string()))
+}
+
+pub fn update_version(path: &str, new_version: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let chart = read_chart(path)?;
+    let new_chart = set_top_level_value(&chart, "version", new_version)
+        .ok_or_else(|| format!("No version found in {}", chart_path(path)))?;
+    fs::write(chart_path(path), new_chart)?;
+    Ok(chart_path(path))
+}
+
+/// Sets `appVersion` and the versions of the in-repo (`file://`) chart dependencies.
+/// Returns the chart path when it changed.
+pub fn update_linked_versions(
+    path: &str,
+    app_version: Option<&str>,
+    dependency_versions: &HashMap<String, String>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let chart = read_chart(path)?;
+    let mut new_chart = update_dependencies(&chart, dependency_versions);
+
+    if let Some(app_version) = app_version {
+        new_chart = match set_top_level_value(&new_chart, "appVersion", app_version) {
+            Some(updated) => updated,
+            None => {
+                let mut appended = new_chart.clone();
+                if !appended.is_empty() && !appended.ends_with('\n') {
+                    appended.push('\n');
+                }
+                appended.push_str(&format!("appVersion: \"{}\"\n", app_version));
+                appended
+            }
+        };
+    }
+
+    if new_chart == chart {
+        return Ok(None);
+    }
+    fs::write(chart_path(path), new_chart)?;
+    Ok(Some(chart_path(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const CHART: &str = "apiVersion: v2\nname: api-chart # the API\nversion: 1.2.0\nappVersion: \"1.0.0\"\n\ndependencies:\n  - name: common\n    version: 0.1.0\n    repository: file://../common\n  - name: postgresql\n    version: 12.1.0\n    repository: https://charts.bitnami.com/bitnami\n";
+
+    #[test]
+    fn test_get_and_update_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("Chart.yaml"), CHART).unwrap();
+
+        let (name, version) = get_version_and_name(path).unwrap();
+        assert_eq!(name, "api-chart");
+        assert_eq!(version, "1.2.0");
+
+        update_version(path, "1.3.0").unwrap();
+        let updated = fs::read_to_string(dir.path().join("Chart.yaml")).unwrap();
+        assert_eq!(updated, CHART.replace("version: 1.2.0", "version: 1.3.0"));
+    }
+
+    #[test]
+    fn test_update_linked_versions() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("Chart.yaml"), CHART).unwrap();
+
+        let versions = HashMap::from([
+            ("common".to_string(), "0.2.0".to_string()),
+            ("postgresql".to_string(), "13.0.0".to_string()),
+        ]);
+        update_linked_versions(path, Some("2.0.0"), &versions).unwrap();
+
+        let updated = fs::read_to_string(dir.path().join("Chart.yaml")).unwrap();
+        assert_eq!(
+            updated,
+            CHART
+                .replace("appVersion: \"1.0.0\"", "appVersion: \"2.0.0\"")
+                .replace("version: 0.1.0", "version: 0.2.0")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use serde_json::Value;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{find_json_value, replace_json_value};
+
+/// Only npm records the versions of the workspace packages: pnpm links them
+/// with `link:` references and yarn with `0.0.0-use.local`.
+const NPM_LOCKFILE: &str = "package-lock.json";
+
+fn normalize_path(path: &str) -> &str {
+    path.trim_start_matches("./").trim_end_matches('/')
+}
+
+/// Updates `packages["<path>"].version` of an npm lockfile.
+fn update_npm_lockfile(
+    content: &str,
+    versions: &HashMap<String, String>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut new_content = content.to_string();
+    for (path, version) in versions {
+        let key_path = ["packages", normalize_path(path), "version"];
+        if find_json_value(&new_content, &key_path)?.is_some() {
+            new_content =
+                replace_json_value(&new_content, &key_path, &Value::String(version.clone()))?;
+        }
+    }
+    Ok(new_content)
+}
+
+/// Updates the versions of the workspace packages listed in `package-lock.json`.
+/// `versions` maps package paths to their new version. Returns the path of the
+/// lockfile when it was modified.
+pub fn update_lockfile(
+    root: &Path,
+    versions: &HashMap<String, String>,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let lockfile_path = root.join(NPM_LOCKFILE);
+    if !lockfile_path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&lockfile_path)?;
+    let new_content = update_npm_lockfile(&content, versions)?;
+    if new_content == content {
+        return Ok(None);
+    }
+    fs::write(&lockfile_path, new_content)?;
+    Ok(Some(NPM_LOCKFILE.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn versions() -> HashMap<String, String> {
+        HashMap::from([("packages/api".to_string(), "1.1.0".to_string())])
+    }
+
+    #[test]
+    fn test_update_npm_lockfile() {
+        let dir = tempdir().unwrap();
+        let content = "{\n  \"name\": \"root\",\n  \"lockfileVersion\": 3,\n  \"packages\": {\n    \"\": {\n      \"workspaces\": [\"packages/*\"]\n    },\n    \"node_modules/api\": {\n      \"resolved\": \"packages/api\",\n      \"link\": true\n    },\n    \"packages/api\": {\n      \"name\": \"api\",\n      \"version\": \"1.0.0\"\n    }\n  }\n}\n";
+        fs::write(dir.path().join("package-lock.json"), content).unwrap();
+
+        let updated = update_lockfile(dir.path(), &versions()).unwrap();
+        assert_eq!(updated.as_deref(), Some("package-lock.json"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("package-lock.json")).unwrap(),
+            content.replace("\"version\": \"1.0.0\"", "\"version\": \"1.1.0\"")
+        );
+    }
+
+    #[test]
+    fn test_workspace_lockfiles_without_versions() {
+        let dir = tempdir().unwrap();
+        let pnpm = "lockfileVersion: '9.0'\n\nimporters:\n\n  .: {}\n\n  packages/api: {}\n\n  packages/web:\n    dependencies:\n      api:\n        specifier: workspace:*\n        version: link:../api\n";
+        let yarn = "__metadata:\n  version: 8\n\n\"api@workspace:packages/api\":\n  version: 0.0.0-use.local\n  resolution: \"api@workspace:packages/api\"\n  languageName: unknown\n  linkType: soft\n";
+        fs::write(dir.path().join("pnpm-lock.yaml"), pnpm).unwrap();
+        fs::write(dir.path().join("yarn.lock"), yarn).unwrap();
+
+        assert!(update_lockfile(dir.path(), &versions()).unwrap().is_none());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pnpm-lock.yaml")).unwrap(),
+            pnpm
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("yarn.lock")).unwrap(),
+            yarn
+        );
+    }
+
+    #[test]
+    fn test_no_lockfile() {
+        let dir = tempdir().unwrap();
+        assert!(update_lockfile(dir.path(), &versions()).unwrap().is_none());
+    }
+}
@@ -1,54 +1,237 @@
-use crate::DryRunConfig;
-use serde_json::{Result, Value};
-use std::fs;
+use crate::{DryRunConfig, GitBackend};
+use serde_json::Result;
+use std::{collections::HashMap, fs, path::Path};
 
-use super::{Manifest, Package};
+use super::{
+    go, helm, lockfile, node, python, simple, Manifest, ManifestFile, Package, PackageType,
+    TagFormat,
+};
 
 pub fn get_manifest() -> Result<Manifest> {
     let file_path = String::from("releaser-manifest.json");
     let manifest_raw =
         fs::read_to_string(file_path).expect("releaser-manifest.json file not found");
-    let packages: Vec<Package> = serde_json::from_str(&manifest_raw)?;
-    Ok(Manifest { packages })
+    parse_manifest(&manifest_raw)
 }
 
-pub fn update_package(package_path: &str, new_version: &str, dry_run: &DryRunConfig) -> Result<()> {
+fn parse_manifest(manifest_raw: &str) -> Result<Manifest> {
+    let mut manifest = match serde_json::from_str(manifest_raw)? {
+        ManifestFile::Packages(packages) => Manifest {
+            packages,
+            ..Default::default()
+        },
+        ManifestFile::Manifest(manifest) => *manifest,
+    };
+    for package in &mut manifest.packages {
+        if package.tag_format.is_none() {
+            package.tag_format = manifest.tag_format.clone();
+        }
+    }
+    Ok(manifest)
+}
+
+/// Writes the new version to the package version file and returns its path.
+pub fn update_package(
+    package: &Package,
+    new_version: &str,
+    dry_run: &DryRunConfig,
+) -> std::result::Result<Option<String>, Box<dyn std::error::Error>> {
     if dry_run.is_dry_run {
         println!(
             "Dry run: Would update {} to version {}",
-            package_path, new_version
+            package.path, new_version
         );
-        return Ok(());
+        return Ok(None);
     }
 
-    let package_json_path = package_path.to_string() + "/package.json";
-    let package_json_raw =
-        fs::read_to_string(&package_json_path).expect("Should have been able to read the file");
+    let version_file = match package.package_type {
+        PackageType::Node => node::update_version(&package.path, new_version)?,
+        PackageType::Python => python::update_version(&package.path, new_version)?,
+        PackageType::Go => {
+            println!(
+                "No version file to update for Go module {}, the tag holds the version",
+                package.path
+            );
+            return Ok(None);
+        }
+        PackageType::Helm => helm::update_version(&package.path, new_version)?,
+        PackageType::Simple => {
+            simple::update_version(&package.path, package.version_file.as_deref(), new_version)?
+        }
+    };
+    Ok(Some(version_file))
+}
 
-    let mut package_json: serde_json::Map<String, Value> =
-        serde_json::from_str(&package_json_raw).expect("Should have been able to parse JSON");
+pub fn get_version_and_name(
+    git: &dyn GitBackend,
+    package: &Package,
+) -> std::result::Result<(String, String), Box<dyn std::error::Error>> {
+    match package.package_type {
+        PackageType::Node => node::get_version_and_name(&package.path),
+        PackageType::Python => python::get_version_and_name(&package.path),
+        PackageType::Go => {
+            let name = go::get_module_path(&package.path)?;
+            let tag_format = get_tag_format(package, &name)?;
+            let version = go::get_version(git, &tag_format)?;
+            Ok((name, version))
+        }
+        PackageType::Helm => helm::get_version_and_name(&package.path),
+        PackageType::Simple => {
+            let name = package
+                .name
+                .clone()
+                .ok_or_else(|| format!("Package {} needs a name in the manifest", package.path))?;
+            let version = simple::get_version(&package.path, package.version_file.as_deref())?;
+            Ok((name, version))
+        }
+    }
+}
 
-    package_json.insert(
-        "version".to_string(),
-        Value::String(new_version.to_string()),
-    );
+/// Updates the versions a released package holds about other manifest packages,
+/// such as a Helm chart `appVersion` and its in-repo chart dependencies.
+/// Returns the updated files.
+pub fn update_linked_versions(
+    git: &dyn GitBackend,
+    packages: &[Package],
+    changed_packages: &HashMap<String, String>,
+    dry_run: &DryRunConfig,
+) -> std::result::Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut updated_files = Vec::new();
+    for package in packages {
+        if package.package_type != PackageType::Helm {
+            continue;
+        }
+        let (name, _) = get_version_and_name(git, package)?;
+        if !changed_packages.contains_key(&name) {
+            continue;
+        }
 
-    fs::write(
-        &package_json_path,
-        serde_json::to_string_pretty(&package_json).unwrap(),
-    )
-    .expect("Failed to write updated package.json");
+        let app_version = match &package.app_version_from {
+            Some(linked_name) => Some(get_linked_version(
+                git,
+                packages,
+                changed_packages,
+                linked_name,
+            )?),
+            None => None,
+        };
 
-    Ok(())
+        if dry_run.is_dry_run {
+            println!("Dry run: Would update linked versions of chart {}", name);
+            continue;
+        }
+        updated_files.extend(helm::update_linked_versions(
+            &package.path,
+            app_version.as_deref(),
+            changed_packages,
+        )?);
+    }
+    Ok(updated_files)
 }
 
-pub fn get_version_and_name(path: &str) -> Result<(String, String)> {
-    let package_json_raw = fs::read_to_string(path.to_string() + "/package.json")
-        .expect("Should have been able to read the file");
+/// Updates the workspace package versions listed in `package-lock.json`, if any,
+/// and returns the lockfile path.
+pub fn update_lockfile(
+    git: &dyn GitBackend,
+    packages: &[Package],
+    changed_packages: &HashMap<String, String>,
+    dry_run: &DryRunConfig,
+) -> std::result::Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut versions = HashMap::new();
+    for package in packages {
+        if package.package_type != PackageType::Node {
+            continue;
+        }
+        let (name, _) = get_version_and_name(git, package)?;
+        if let Some(version) = changed_packages.get(&name) {
+            versions.insert(package.path.clone(), version.clone());
+        }
+    }
 
-    let package_json: Value = serde_json::from_str(&package_json_raw)?;
+    if versions.is_empty() {
+        return Ok(None);
+    }
+    if dry_run.is_dry_run {
+        println!("Dry run: Would update the lockfile versions");
+        return Ok(None);
+    }
+    let lockfile = lockfile::update_lockfile(Path::new("."), &versions)?;
+    if let Some(lockfile) = &lockfile {
+        println!("Updated versions in lockfile: {}", lockfile);
+    }
+    Ok(lockfile)
+}
 
-    let version = package_json["version"].as_str().unwrap();
-    let name = package_json["name"].as_str().unwrap();
-    Ok((name.to_string(), version.to_string()))
+fn get_linked_version(
+    git: &dyn GitBackend,
+    packages: &[Package],
+    changed_packages: &HashMap<String, String>,
+    linked_name: &str,
+) -> std::result::Result<String, Box<dyn std::error::Error>> {
+    if let Some(version) = changed_packages.get(linked_name) {
+        return Ok(version.clone());
+    }
+    for package in packages {
+        let (name, version) = get_version_and_name(git, package)?;
+        if name == linked_name {
+            return Ok(version);
+        }
+    }
+    Err(format!("Linked package {} not found in the manifest", linked_name).into())
+}
+
+pub fn get_tag_format(
+    package: &Package,
+    name: &str,
+) -> std::result::Result<TagFormat, Box<dyn std::error::Error>> {
+    let template = match (&package.tag_format, &package.package_type) {
+        (Some(template), _) => template.clone(),
+        (None, PackageType::Go) => format!("{}{{version}}", go::get_tag_prefix(&package.path)),
+        (None, _) => "{name}-v{version}".to_string(),
+    };
+    TagFormat::new(&template, name, &package.path)
+}
+
+pub fn get_version_warning(package: &Package, name: &str, new_version: &str) -> Option<String> {
+    match package.package_type {
+        PackageType::Go => go::get_major_version_warning(name, new_version),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = parse_manifest(r#"[{"path": "packages/api"}]"#).unwrap();
+        assert_eq!(manifest.packages.len(), 1);
+        assert!(manifest.tag_format.is_none());
+
+        let manifest = parse_manifest(
+            r#"{
+                "tagFormat": "{unscopedName}@{version}",
+                "sharedPaths": [{"path": "tsconfig.base.json", "packages": ["web"]}],
+                "packages": [
+                    {"path": "packages/api"},
+                    {"path": "packages/web", "tagFormat": "web/{version}"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.packages[0].tag_format.as_deref(),
+            Some("{unscopedName}@{version}")
+        );
+        assert_eq!(
+            manifest.packages[1].tag_format.as_deref(),
+            Some("web/{version}")
+        );
+
+        assert_eq!(manifest.shared_paths[0].packages, vec!["web"]);
+
+        let tag_format = get_tag_format(&manifest.packages[0], "@acme/api").unwrap();
+        assert_eq!(tag_format.tag("1.0.0"), "api@1.0.0");
+    }
 }
@@ -1,4 +1,20 @@
-pub use self::manager::{get_manifest, get_version_and_name, update_package};
-pub use self::types::{Manifest, Package};
+pub use self::manager::{
+    get_manifest, get_tag_format, get_version_and_name, get_version_warning,
+    update_linked_versions, update_lockfile, update_package,
+};
+pub use self::path_rules::PathRules;
+pub use self::tag_format::TagFormat;
+pub use self::types::{
+    CommitMessage, ExtraFile, ExtraFileType, Manifest, ManifestFile, Package, PackageType, Signing,
+    StructuredExtraFile,
+};
+mod go;
+mod helm;
+mod lockfile;
 mod manager;
+mod node;
+mod path_rules;
+mod python;
+mod simple;
+mod tag_format;
 mod types;
@@ -0,0 +1,72 @@
+use serde_json::Value;
+use std::fs;
+
+use crate::replace_json_value;
+
+pub fn get_version_and_name(path: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let package_json_path = path.to_string() + "/package.json";
+    let package_json_raw = fs::read_to_string(&package_json_path)
+        .map_err(|e| format!("Failed to read {}: {}", package_json_path, e))?;
+
+    let package_json: Value = serde_json::from_str(&package_json_raw)
+        .map_err(|e| format!("Failed to parse {}: {}", package_json_path, e))?;
+
+    let version = package_json["version"]
+        .as_str()
+        .ok_or_else(|| format!("No version found in {}", package_json_path))?;
+    let name = package_json["name"]
+        .as_str()
+        .ok_or_else(|| format!("No package name found in {}", package_json_path))?;
+    Ok((name.to_string(), version.to_string()))
+}
+
+pub fn update_version(path: &str, new_version: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let package_json_path = path.to_string() + "/package.json";
+    let package_json_raw = fs::read_to_string(&package_json_path)
+        .map_err(|e| format!("Failed to read {}: {}", package_json_path, e))?;
+
+    let updated_package_json = replace_json_value(
+        &package_json_raw,
+        &["version"],
+        &Value::String(new_version.to_string()),
+    )?;
+
+    fs::write(&package_json_path, updated_package_json)
+        .map_err(|e| format!("Failed to write {}: {}", package_json_path, e))?;
+
+    Ok(package_json_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_update_version_preserves_formatting() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let content = "{\n    \"name\": \"api\",\n    \"version\": \"1.0.1\",\n    \"main\": \"index.js\"\n}\n";
+        fs::write(dir.path().join("package.json"), content).unwrap();
+
+        update_version(path, "1.1.0").unwrap();
+
+        let updated = fs::read_to_string(dir.path().join("package.json")).unwrap();
+        assert_eq!(updated, content.replace("1.0.1", "1.1.0"));
+    }
+
+    #[test]
+    fn test_invalid_package_json() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let error = get_version_and_name(path).unwrap_err().to_string();
+        assert!(error.starts_with(&format!("Failed to read {}/package.json", path)));
+        assert!(update_version(path, "1.1.0").is_err());
+
+        fs::write(dir.path().join("package.json"), "{\"name\": \"api\"}\n").unwrap();
+        assert_eq!(
+            get_version_and_name(path).unwrap_err().to_string(),
+            format!("No version found in {}/package.json", path)
+        );
+    }
+}
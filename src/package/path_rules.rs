@@ -0,0 +1,199 @@
+use glob::{MatchOptions, Pattern};
+
+use super::{Manifest, Package};
+
+/// Files whose changes release a package: the files under its path that match
+/// its `include` and `exclude` globs, outside of the manifest packages nested in
+/// it, and the shared paths it depends on.
+#[derive(Debug, Clone)]
+pub struct PathRules {
+    path: String,
+    nested_paths: Vec<String>,
+    shared_paths: Vec<String>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+fn normalize(path: &str) -> &str {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if path == "." {
+        ""
+    } else {
+        path
+    }
+}
+
+/// Returns the path of `file` relative to the directory `dir`, if it is under it.
+fn relative_to<'a>(file: &'a str, dir: &str) -> Option<&'a str> {
+    if dir.is_empty() {
+        return Some(file);
+    }
+    if file == dir {
+        return Some("");
+    }
+    file.strip_prefix(dir)?.strip_prefix('/')
+}
+
+fn compile(patterns: &[String], path: &str) -> Result<Vec<Pattern>, Box<dyn std::error::Error>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Pattern::new(pattern)
+                .map_err(|e| format!("Invalid glob {} in package {}: {}", pattern, path, e).into())
+        })
+        .collect()
+}
+
+impl PathRules {
+    pub fn new(
+        package: &Package,
+        name: &str,
+        manifest: &Manifest,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = normalize(&package.path);
+        let nested_paths = if package.include_nested {
+            Vec::new()
+        } else {
+            manifest
+                .packages
+                .iter()
+                .map(|other| normalize(&other.path))
+                .filter(|other| other != &path && relative_to(other, path).is_some())
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(PathRules {
+            path: path.to_string(),
+            nested_paths,
+            shared_paths: manifest
+                .shared_paths
+                .iter()
+                .filter(|shared| {
+                    shared.packages.is_empty() || shared.packages.iter().any(|p| p == name)
+                })
+                .map(|shared| normalize(&shared.path).to_string())
+                .collect(),
+            include: compile(&package.include, &package.path)?,
+            exclude: compile(&package.exclude, &package.path)?,
+        })
+    }
+
+    /// Paths to restrict `git diff` and `git log` to.
+    pub fn pathspecs(&self) -> Vec<&str> {
+        let mut pathspecs = vec![if self.path.is_empty() {
+            "."
+        } else {
+            &self.path
+        }];
+        pathspecs.extend(self.shared_paths.iter().map(String::as_str));
+        pathspecs
+    }
+
+    /// Whether the files under the pathspecs need to be matched one by one.
+    pub fn filters_files(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty() || !self.nested_paths.is_empty()
+    }
+
+    /// Whether a change to `file`, relative to the repository root, releases the package.
+    pub fn matches(&self, file: &str) -> bool {
+        if self
+            .shared_paths
+            .iter()
+            .any(|shared| relative_to(file, shared).is_some())
+        {
+            return true;
+        }
+        let Some(relative) = relative_to(file, &self.path) else {
+            return false;
+        };
+        if self
+            .nested_paths
+            .iter()
+            .any(|nested| relative_to(file, nested).is_some())
+        {
+            return false;
+        }
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let matches = |pattern: &Pattern| pattern.matches_with(relative, options);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(value: serde_json::Value) -> Manifest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_path_rules() {
+        let manifest = manifest(serde_json::json!({
+            "sharedPaths": [
+                {"path": "tsconfig.base.json"},
+                {"path": "./packages/config/", "packages": ["web"]}
+            ],
+            "packages": [{
+                "path": "./packages/api/",
+                "include": ["src/**", "package.json"],
+                "exclude": ["**/*.test.ts", "**/*.stories.tsx"]
+            }]
+        }));
+        let rules = PathRules::new(&manifest.packages[0], "api", &manifest).unwrap();
+        assert_eq!(
+            rules.pathspecs(),
+            vec!["packages/api", "tsconfig.base.json"]
+        );
+        assert!(rules.filters_files());
+
+        assert!(rules.matches("packages/api/src/orders/index.ts"));
+        assert!(rules.matches("packages/api/package.json"));
+        assert!(rules.matches("tsconfig.base.json"));
+        assert!(!rules.matches("packages/api/src/orders/index.test.ts"));
+        assert!(!rules.matches("packages/api/README.md"));
+        assert!(!rules.matches("packages/api-client/src/index.ts"));
+        assert!(!rules.matches("packages/config/eslint.js"));
+
+        let package: Package = serde_json::from_value(serde_json::json!({"path": "."})).unwrap();
+        let rules = PathRules::new(&package, "web", &manifest).unwrap();
+        assert_eq!(
+            rules.pathspecs(),
+            vec![".", "tsconfig.base.json", "packages/config"]
+        );
+        assert!(rules.matches("README.md"));
+
+        let package: Package =
+            serde_json::from_value(serde_json::json!({"path": "api", "exclude": ["src/["]}))
+                .unwrap();
+        assert!(PathRules::new(&package, "api", &manifest).is_err());
+    }
+
+    #[test]
+    fn test_nested_packages() {
+        let mut manifest = manifest(serde_json::json!({"packages": [
+            {"path": "packages/app"},
+            {"path": "./packages/app/plugins/foo/"},
+            {"path": "packages/application"}
+        ]}));
+        let rules = PathRules::new(&manifest.packages[0], "app", &manifest).unwrap();
+        assert!(rules.filters_files());
+        assert!(rules.matches("packages/app/src/index.ts"));
+        assert!(rules.matches("packages/app/plugins/bar/index.ts"));
+        assert!(!rules.matches("packages/app/plugins/foo/index.ts"));
+        assert!(!rules.matches("packages/application/index.ts"));
+
+        let rules = PathRules::new(&manifest.packages[1], "foo", &manifest).unwrap();
+        assert!(!rules.filters_files());
+        assert!(rules.matches("packages/app/plugins/foo/index.ts"));
+
+        manifest.packages[0].include_nested = true;
+        let rules = PathRules::new(&manifest.packages[0], "app", &manifest).unwrap();
+        assert!(!rules.filters_files());
+        assert!(rules.matches("packages/app/plugins/foo/index.ts"));
+    }
+}
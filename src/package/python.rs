@@ -0,0 +1,102 @@
+use std::fs;
+use toml_edit::{DocumentMut, Item};
+
+use crate::{from_pep440, replace_toml_value, to_pep440};
+
+const VERSION_TABLES: [&[&str]; 2] = [&["project"], &["tool", "poetry"]];
+
+fn find_table<'a>(document: &'a DocumentMut, keys: &[&str]) -> Option<&'a Item> {
+    let mut item = document.as_item();
+    for key in keys {
+        item = item.get(key)?;
+    }
+    item.get("version").map(|_| item)
+}
+
+pub fn get_version_and_name(path: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let pyproject_path = path.to_string() + "/pyproject.toml";
+    let pyproject_raw = fs::read_to_string(&pyproject_path)
+        .map_err(|e| format!("Failed to read {}: {}", pyproject_path, e))?;
+    let document = pyproject_raw.parse::<DocumentMut>()?;
+
+    let table = VERSION_TABLES
+        .iter()
+        .find_map(|keys| find_table(&document, keys))
+        .ok_or_else(|| {
+            format!(
+                "No [project] or [tool.poetry] version found in {}",
+                pyproject_path
+            )
+        })?;
+
+    let version = table["version"]
+        .as_str()
+        .ok_or_else(|| format!("Version in {} is not a string", pyproject_path))?;
+    let name = table
+        .get("name")
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| format!("No package name found in {}", pyproject_path))?;
+
+    Ok((name.to_string(), from_pep440(version)))
+}
+
+pub fn update_version(path: &str, new_version: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let pyproject_path = path.to_string() + "/pyproject.toml";
+    let pyproject_raw = fs::read_to_string(&pyproject_path)
+        .map_err(|e| format!("Failed to read {}: {}", pyproject_path, e))?;
+    let document = pyproject_raw.parse::<DocumentMut>()?;
+
+    let keys = VERSION_TABLES
+        .iter()
+        .find(|keys| find_table(&document, keys).is_some())
+        .ok_or_else(|| {
+            format!(
+                "No [project] or [tool.poetry] version found in {}",
+                pyproject_path
+            )
+        })?;
+    let version_path: Vec<&str> = keys.iter().copied().chain(["version"]).collect();
+
+    let updated_pyproject =
+        replace_toml_value(&pyproject_raw, &version_path, &to_pep440(new_version))?;
+    fs::write(&pyproject_path, updated_pyproject)?;
+    Ok(pyproject_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pep621_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let content = "[project]\nname = \"ml-service\"\nversion   = \"1.2.0b1\"  # current\n\n[tool.black]\nline-length = 100\n";
+        fs::write(dir.path().join("pyproject.toml"), content).unwrap();
+
+        let (name, version) = get_version_and_name(path).unwrap();
+        assert_eq!(name, "ml-service");
+        assert_eq!(version, "1.2.0-beta.1");
+
+        update_version(path, "1.2.0-beta.2").unwrap();
+        let updated = fs::read_to_string(dir.path().join("pyproject.toml")).unwrap();
+        assert_eq!(updated, content.replace("\"1.2.0b1\"", "\"1.2.0b2\""));
+    }
+
+    #[test]
+    fn test_poetry_version() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let content = "[tool.poetry]\nname = \"trainer\"\nversion = \"0.3.1\"\n\n[tool.poetry.dependencies]\npython = \"^3.11\"\n";
+        fs::write(dir.path().join("pyproject.toml"), content).unwrap();
+
+        let (name, version) = get_version_and_name(path).unwrap();
+        assert_eq!(name, "trainer");
+        assert_eq!(version, "0.3.1");
+
+        update_version(path, "0.4.0").unwrap();
+        let updated = fs::read_to_string(dir.path().join("pyproject.toml")).unwrap();
+        assert_eq!(updated, content.replace("0.3.1", "0.4.0"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs;
+
+const DEFAULT_VERSION_FILE: &str = "VERSION";
+
+fn version_file_path(path: &str, version_file: Option<&str>) -> String {
+    format!("{}/{}", path, version_file.unwrap_or(DEFAULT_VERSION_FILE))
+}
+
+pub fn get_version(
+    path: &str,
+    version_file: Option<&str>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let version_file_path = version_file_path(path, version_file);
+    let contents = fs::read_to_string(&version_file_path)
+        .map_err(|e| format!("Failed to read {}: {}", version_file_path, e))?;
+
+    let version = contents.trim();
+    if version.is_empty() {
+        return Err(format!("{} is empty", version_file_path).into());
+    }
+    Ok(version.to_string())
+}
+
+pub fn update_version(
+    path: &str,
+    version_file: Option<&str>,
+    new_version: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let version_file_path = version_file_path(path, version_file);
+    let contents = fs::read_to_string(&version_file_path)
+        .map_err(|e| format!("Failed to read {}: {}", version_file_path, e))?;
+
+    let mut new_contents = new_version.to_string();
+    // Preserve the original file's ending (with or without newline)
+    if contents.ends_with("\r\n") {
+        new_contents.push_str("\r\n");
+    } else if contents.ends_with('\n') {
+        new_contents.push('\n');
+    }
+
+    fs::write(&version_file_path, new_contents)?;
+    Ok(version_file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_version_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("VERSION"), "1.4.2\n").unwrap();
+
+        assert_eq!(get_version(path, None).unwrap(), "1.4.2");
+
+        update_version(path, None, "1.5.0").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("VERSION")).unwrap(),
+            "1.5.0\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_version_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        fs::write(dir.path().join(".version"), "0.1.0").unwrap();
+
+        assert_eq!(get_version(path, Some(".version")).unwrap(), "0.1.0");
+
+        update_version(path, Some(".version"), "0.2.0-beta").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".version")).unwrap(),
+            "0.2.0-beta"
+        );
+        assert!(get_version(path, None).is_err());
+    }
+}
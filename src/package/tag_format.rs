@@ -0,0 +1,95 @@
+use regex::Regex;
+
+/// Tag template of a package, such as `{name}-v{version}`, with every placeholder
+/// but `{version}` already resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagFormat {
+    prefix: String,
+    suffix: String,
+}
+
+impl TagFormat {
+    pub fn new(template: &str, name: &str, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (prefix, suffix) = template
+            .split_once("{version}")
+            .filter(|(_, suffix)| !suffix.contains("{version}"))
+            .ok_or_else(|| {
+                format!(
+                    "Tag format {} must contain {{version}} exactly once",
+                    template
+                )
+            })?;
+
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        let unscoped_name = match name.strip_prefix('@') {
+            Some(scoped) => scoped.split_once('/').map_or(scoped, |(_, name)| name),
+            None => name,
+        };
+        let resolve = |part: &str| -> Result<String, Box<dyn std::error::Error>> {
+            let resolved = part
+                .replace("{unscopedName}", unscoped_name)
+                .replace("{name}", name)
+                .replace("{path}", path);
+            match Regex::new(r"\{\w*\}").unwrap().find(&resolved) {
+                Some(placeholder) => Err(format!(
+                    "Unknown placeholder {} in tag format {}",
+                    placeholder.as_str(),
+                    template
+                )
+                .into()),
+                None => Ok(resolved),
+            }
+        };
+
+        Ok(TagFormat {
+            prefix: resolve(prefix)?,
+            suffix: resolve(suffix)?,
+        })
+    }
+
+    pub fn tag(&self, version: &str) -> String {
+        format!("{}{}{}", self.prefix, version, self.suffix)
+    }
+
+    /// Glob listing the candidate tags of the package.
+    pub fn pattern(&self) -> String {
+        format!("{}*{}", self.prefix, self.suffix)
+    }
+
+    /// Extracts the version of a tag, if the tag follows the format.
+    pub fn version<'a>(&self, tag: &'a str) -> Option<&'a str> {
+        let version = tag.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)?;
+        version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then_some(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_format() {
+        let format = TagFormat::new("{name}-v{version}", "api", "packages/api").unwrap();
+        assert_eq!(format.tag("1.2.0"), "api-v1.2.0");
+        assert_eq!(format.pattern(), "api-v*");
+        assert_eq!(format.version("api-v1.2.0-beta.1"), Some("1.2.0-beta.1"));
+        assert_eq!(format.version("api-vnext"), None);
+        assert_eq!(format.version("web-v1.2.0"), None);
+
+        let format = TagFormat::new("{unscopedName}@{version}", "@acme/api", ".").unwrap();
+        assert_eq!(format.tag("1.2.0"), "api@1.2.0");
+
+        let format = TagFormat::new("{path}/v{version}", "api", "./services/api/").unwrap();
+        assert_eq!(format.tag("1.2.0"), "services/api/v1.2.0");
+
+        let format = TagFormat::new("release-{version}-final", "api", ".").unwrap();
+        assert_eq!(format.pattern(), "release-*-final");
+        assert_eq!(format.version("release-2.0.0-final"), Some("2.0.0"));
+
+        assert!(TagFormat::new("{name}", "api", ".").is_err());
+        assert!(TagFormat::new("{version}-{version}", "api", ".").is_err());
+        assert!(TagFormat::new("{scope}-{version}", "api", ".").is_err());
+    }
+}
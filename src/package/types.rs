@@ -1,16 +1,188 @@
 use serde::{Deserialize, Serialize};
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageType {
+    #[default]
+    Node,
+    Python,
+    Go,
+    Helm,
+    Simple,
+}
+
+impl PackageType {
+    /// Whether the package stores its version in a file, or only in its tags.
+    pub fn has_version_file(&self) -> bool {
+        !matches!(self, PackageType::Go)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraFileType {
+    Json,
+    Yaml,
+    Toml,
+    /// Android `build.gradle`: increments `versionCode` and sets `versionName`
+    Gradle,
+    /// iOS `Info.plist`: increments `CFBundleVersion` and sets `CFBundleShortVersionString`
+    Plist,
+}
+
+/// Extra file whose version is located by a path in its structure, for files
+/// that cannot hold an `x-releaser-version` comment.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StructuredExtraFile {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub file_type: ExtraFileType,
+    #[serde(default)]
+    pub jsonpath: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExtraFile {
+    Marker(String),
+    Structured(StructuredExtraFile),
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Package {
     pub path: String,
+    /// Package name, required by the packages whose version file has no name
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "type")]
+    pub package_type: PackageType,
     #[serde(default)]
     #[serde(rename = "extraFiles")]
-    pub extra_files: Vec<String>,
+    pub extra_files: Vec<ExtraFile>,
     #[serde(default)]
     pub dependencies: Vec<String>,
+    /// Name of the manifest package whose version is used as the chart `appVersion`
+    #[serde(default)]
+    #[serde(rename = "appVersionFrom")]
+    pub app_version_from: Option<String>,
+    /// Version file of a `simple` package, relative to its path
+    #[serde(default)]
+    #[serde(rename = "versionFile")]
+    pub version_file: Option<String>,
+    /// Tag template such as `{name}-v{version}`, defaults to the manifest one
+    #[serde(default)]
+    #[serde(rename = "tagFormat")]
+    pub tag_format: Option<String>,
+    /// Commit to diff from while the package has no release tag
+    #[serde(default)]
+    #[serde(rename = "bootstrapSha")]
+    pub bootstrap_sha: Option<String>,
+    /// Globs relative to the path of the files whose changes release the package,
+    /// defaults to every file
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs relative to the path of the files whose changes are ignored
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Count the changes of the manifest packages nested in the path
+    #[serde(default)]
+    #[serde(rename = "includeNested")]
+    pub include_nested: bool,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// File or directory outside of the package paths whose changes release the
+/// listed packages, or every package when none is listed.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SharedPath {
+    pub path: String,
+    /// Names of the packages released by a change
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct Manifest {
     pub packages: Vec<Package>,
+    /// Tag template of the packages without their own
+    #[serde(default)]
+    #[serde(rename = "tagFormat")]
+    pub tag_format: Option<String>,
+    #[serde(default)]
+    pub signing: Signing,
+    /// Create lightweight tags instead of tags annotated with the release notes
+    #[serde(default)]
+    #[serde(rename = "lightweightTags")]
+    pub lightweight_tags: bool,
+    /// Remote used by `--push`, defaults to `origin`
+    #[serde(default)]
+    pub remote: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "commitMessage")]
+    pub commit_message: CommitMessage,
+    #[serde(default)]
+    #[serde(rename = "sharedPaths")]
+    pub shared_paths: Vec<SharedPath>,
+}
+
+/// Template of the release commit message. `{packages}` lists the released
+/// packages as `name@version`, `{count}` counts them.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CommitMessage {
+    #[serde(default = "default_commit_subject")]
+    pub subject: String,
+    /// Body line of each released package, with `{name}` and `{version}`
+    #[serde(default = "default_commit_line")]
+    pub line: String,
+    /// Appends `[skip ci]` to the subject
+    #[serde(default)]
+    #[serde(rename = "skipCi")]
+    pub skip_ci: bool,
+    /// Trailer lines such as `Release-Packages: {packages}`
+    #[serde(default)]
+    pub trailers: Vec<String>,
+}
+
+fn default_commit_subject() -> String {
+    "chore(release): bump packages".to_string()
+}
+
+fn default_commit_line() -> String {
+    "- {name}: {version}".to_string()
+}
+
+impl Default for CommitMessage {
+    fn default() -> Self {
+        CommitMessage {
+            subject: default_commit_subject(),
+            line: default_commit_line(),
+            skip_ci: false,
+            trailers: Vec::new(),
+        }
+    }
+}
+
+/// Signing of the release commits and tags, with the key and format configured
+/// in git unless overridden.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Signing {
+    #[serde(default)]
+    pub commits: bool,
+    #[serde(default)]
+    pub tags: bool,
+    /// GPG key id, or path to the SSH key with the `ssh` format
+    #[serde(default)]
+    pub key: Option<String>,
+    /// `openpgp`, `ssh` or `x509`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+/// The manifest is either a list of packages or an object holding the packages
+/// along with the global options.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ManifestFile {
+    Packages(Vec<Package>),
+    Manifest(Box<Manifest>),
 }
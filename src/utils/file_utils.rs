@@ -1,49 +1,305 @@
-use std::fs;
+use regex::Regex;
+use serde_json::Value;
+use std::{fs, path::Path};
 
-use crate::DryRunConfig;
+use super::{
+    parse_jsonpath, replace_json_value, replace_toml_value, replace_yaml_value,
+    update_gradle_versions, update_plist_versions,
+};
+use crate::{log_warning, DryRunConfig, ExtraFile, ExtraFileType, StructuredExtraFile};
 
-pub fn increase_extra_files_version(
-    extra_files: &Vec<String>,
-    new_version: &str,
-    dry_run: &DryRunConfig,
-) {
-    for extra_file in extra_files {
-        let contents = fs::read_to_string(extra_file).expect("Failed to read file");
+/// Comment openers a marker can follow: `//`, `#`, `--`, `<!--`, `/*` and `;`.
+const COMMENT_OPENERS: &str = r"(?://|#|--|<!--|/\*|;)";
+
+const VERSION_PATTERN: &str = r"\d+\.\d+\.\d+(?:-[a-zA-Z0-9.]+)?";
+
+/// Derived form of the version a marker replaces, such as `{major}.{minor}` or
+/// `v{major}`. Without a template, the full version is replaced.
+struct VersionTemplate {
+    template: String,
+    pattern: Regex,
+}
+
+impl VersionTemplate {
+    fn new(template: Option<&str>) -> Self {
+        let template = template
+            .map(|template| template.trim_end_matches("-->").trim_end_matches("*/"))
+            .filter(|template| !template.is_empty())
+            .unwrap_or("{version}");
+
+        let placeholder = Regex::new(r"\{(version|major|minor|patch|prerelease)\}").unwrap();
+        let mut pattern = String::new();
+        let mut last_end = 0;
+        for captures in placeholder.captures_iter(template) {
+            let placeholder_match = captures.get(0).unwrap();
+            pattern.push_str(&regex::escape(
+                &template[last_end..placeholder_match.start()],
+            ));
+            pattern.push_str(match &captures[1] {
+                "version" => VERSION_PATTERN,
+                "prerelease" => r"[a-zA-Z0-9.]*",
+                _ => r"\d+",
+            });
+            last_end = placeholder_match.end();
+        }
+        pattern.push_str(&regex::escape(&template[last_end..]));
+
+        VersionTemplate {
+            template: template.to_string(),
+            pattern: Regex::new(&pattern).unwrap(),
+        }
+    }
+
+    /// Replaces the matches of the template in `text` that are not part of a longer
+    /// version, such as `2.3` in `2.3.4`. A template without `{version}` can also
+    /// match unrelated numbers, so with `nearest_only` it only replaces its last
+    /// match, the closest to the marker.
+    fn replace(&self, text: &str, version: &str, nearest_only: bool) -> String {
+        let mut matches: Vec<_> = self
+            .pattern
+            .find_iter(text)
+            .filter(|found| is_standalone(text, found.start(), found.end()))
+            .collect();
+        if nearest_only && !self.template.contains("{version}") {
+            matches = matches.split_off(matches.len().saturating_sub(1));
+        }
+
+        let rendered = self.render(version);
+        let mut new_text = String::with_capacity(text.len());
+        let mut last_end = 0;
+        for found in matches {
+            new_text.push_str(&text[last_end..found.start()]);
+            new_text.push_str(&rendered);
+            last_end = found.end();
+        }
+        new_text.push_str(&text[last_end..]);
+        new_text
+    }
+
+    fn render(&self, version: &str) -> String {
+        let (core, prerelease) = version.split_once('-').unwrap_or((version, ""));
+        let mut numbers = core.split('.');
+        let major = numbers.next().unwrap_or("0");
+        let minor = numbers.next().unwrap_or("0");
+        let patch = numbers.next().unwrap_or("0");
+
+        self.template
+            .replace("{version}", version)
+            .replace("{major}", major)
+            .replace("{minor}", minor)
+            .replace("{patch}", patch)
+            .replace("{prerelease}", prerelease)
+    }
+}
+
+/// Whether `text[start..end]` is not glued to the digits of a longer version.
+fn is_standalone(text: &str, start: usize, end: usize) -> bool {
+    let is_digit = |c: char| c.is_ascii_digit();
+    let mut before = text[..start].chars().rev();
+    let mut after = text[end..].chars();
+    let extends_before = text[start..].starts_with(is_digit)
+        && before.next().is_some_and(|c| is_digit(c) || c == '.');
+    let extends_after = text[..end].ends_with(is_digit)
+        && match after.next() {
+            Some('.') => after.next().is_some_and(is_digit),
+            next => next.is_some_and(is_digit),
+        };
+    !extends_before && !extends_after
+}
+
+/// Markers flagging the lines whose version must be replaced: a single line ending
+/// with `x-releaser-version`, or every line between `x-releaser-start` and
+/// `x-releaser-end`. Both can take a template, like `x-releaser-version:{major}`.
+struct VersionMarkers {
+    line: Regex,
+    block_start: Regex,
+    block_end: Regex,
+}
+
+impl VersionMarkers {
+    fn new() -> Self {
+        let marker = |name: &str| {
+            Regex::new(&format!(r"{}\s*{}(?::(\S+)|\b)", COMMENT_OPENERS, name)).unwrap()
+        };
+        VersionMarkers {
+            line: marker("x-releaser-version"),
+            block_start: marker("x-releaser-start"),
+            block_end: marker("x-releaser-end"),
+        }
+    }
 
-        let mut new_contents: String = contents
-            .lines()
+    fn update(&self, contents: &str, new_version: &str) -> String {
+        let mut block_template: Option<VersionTemplate> = None;
+
+        contents
+            .split_inclusive('\n')
             .map(|line| {
-                if line.contains("// x-releaser-version") {
-                    let parts: Vec<&str> = line.split("// x-releaser-version").collect();
-                    let version_pattern =
-                        regex::Regex::new(r"\d+\.\d+\.\d+(-[a-zA-Z0-9.]+)?").unwrap();
-
-                    if let Some(version_match) = version_pattern.find(parts[0]) {
-                        let old_version = version_match.as_str();
-                        line.replace(old_version, new_version)
-                    } else {
-                        line.to_string()
-                    }
+                if let Some(captures) = self.block_start.captures(line) {
+                    block_template = Some(VersionTemplate::new(
+                        captures.get(1).map(|template| template.as_str()),
+                    ));
+                    line.to_string()
+                } else if self.block_end.is_match(line) {
+                    block_template = None;
+                    line.to_string()
+                } else if let Some(template) = &block_template {
+                    template.replace(line, new_version, false)
+                } else if let Some(captures) = self.line.captures(line) {
+                    let template =
+                        VersionTemplate::new(captures.get(1).map(|template| template.as_str()));
+                    let (code, comment) = line.split_at(captures.get(0).unwrap().start());
+                    format!("{}{}", template.replace(code, new_version, true), comment)
                 } else {
                     line.to_string()
                 }
             })
-            .collect::<Vec<String>>()
-            .join("\n");
+            .collect()
+    }
+}
+
+fn is_glob_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
 
-        // Preserve the original file's ending (with or without newline)
-        if contents.ends_with('\n') {
-            new_contents.push('\n');
+fn expand_glob(pattern: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for entry in glob::glob(pattern).map_err(|e| format!("Invalid pattern {}: {}", pattern, e))? {
+        let path = entry?;
+        if path.is_file() {
+            paths.push(path.to_string_lossy().to_string());
         }
+    }
+    Ok(paths)
+}
 
-        if !dry_run.is_dry_run {
-            fs::write(extra_file, new_contents).expect("Failed to write to file");
-        } else {
-            println!("Dry run: Would update version in file: {}", extra_file);
+/// Resolves an extra file path or glob pattern. Paths starting with `{path}/` are
+/// relative to the package directory, the others to the repository root.
+fn resolve_extra_file_paths(
+    package_path: &str,
+    path: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let resolved_path = match path.strip_prefix("{path}/") {
+        Some(package_relative_path) => Path::new(package_path)
+            .join(package_relative_path)
+            .to_string_lossy()
+            .to_string(),
+        None => path.to_string(),
+    };
+
+    if is_glob_pattern(&resolved_path) {
+        let paths = expand_glob(&resolved_path)?;
+        if paths.is_empty() {
+            log_warning(&format!(
+                "No extra file matches the pattern {}",
+                resolved_path
+            ));
         }
+        return Ok(paths);
+    }
+
+    if Path::new(&resolved_path).is_file() {
+        Ok(vec![resolved_path])
+    } else {
+        Err(format!("Extra file {} not found", resolved_path).into())
+    }
+}
 
-        println!("Updated version in file: {}", extra_file);
+fn increase_marker_file_version(
+    extra_file: &str,
+    new_version: &str,
+    dry_run: &DryRunConfig,
+    markers: &VersionMarkers,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(extra_file)
+        .map_err(|e| format!("Failed to read {}: {}", extra_file, e))?;
+    let new_contents = markers.update(&contents, new_version);
+
+    if !dry_run.is_dry_run {
+        fs::write(extra_file, new_contents)
+            .map_err(|e| format!("Failed to write {}: {}", extra_file, e))?;
+    } else {
+        println!("Dry run: Would update version in file: {}", extra_file);
     }
+
+    println!("Updated version in file: {}", extra_file);
+    Ok(())
+}
+
+fn increase_structured_file_version(
+    extra_file: &str,
+    structured_file: &StructuredExtraFile,
+    new_version: &str,
+    dry_run: &DryRunConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let keys = match structured_file.jsonpath.as_deref() {
+        Some(jsonpath) => parse_jsonpath(jsonpath)?,
+        None => Vec::new(),
+    };
+    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let needs_jsonpath = matches!(
+        structured_file.file_type,
+        ExtraFileType::Json | ExtraFileType::Yaml | ExtraFileType::Toml
+    );
+    if needs_jsonpath && keys.is_empty() {
+        return Err(format!("Extra file {} needs a jsonpath", structured_file.path).into());
+    }
+
+    let contents = fs::read_to_string(extra_file)
+        .map_err(|e| format!("Failed to read {}: {}", extra_file, e))?;
+    let new_contents = match structured_file.file_type {
+        ExtraFileType::Json => {
+            replace_json_value(&contents, &keys, &Value::String(new_version.to_string()))
+        }
+        ExtraFileType::Yaml => replace_yaml_value(&contents, &keys, new_version),
+        ExtraFileType::Toml => replace_toml_value(&contents, &keys, new_version),
+        ExtraFileType::Gradle => update_gradle_versions(&contents, new_version),
+        ExtraFileType::Plist => update_plist_versions(&contents, new_version),
+    }
+    .map_err(|e| format!("Failed to update {}: {}", extra_file, e))?;
+
+    if !dry_run.is_dry_run {
+        fs::write(extra_file, new_contents)
+            .map_err(|e| format!("Failed to write {}: {}", extra_file, e))?;
+    } else {
+        println!("Dry run: Would update version in file: {}", extra_file);
+    }
+
+    println!("Updated version in file: {}", extra_file);
+    Ok(())
+}
+
+pub fn increase_extra_files_version(
+    package_path: &str,
+    extra_files: &[ExtraFile],
+    new_version: &str,
+    dry_run: &DryRunConfig,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let markers = VersionMarkers::new();
+    let mut updated_files = Vec::new();
+
+    for extra_file in extra_files {
+        match extra_file {
+            ExtraFile::Marker(path) => {
+                for resolved_path in resolve_extra_file_paths(package_path, path)? {
+                    increase_marker_file_version(&resolved_path, new_version, dry_run, &markers)?;
+                    updated_files.push(resolved_path);
+                }
+            }
+            ExtraFile::Structured(structured_file) => {
+                for resolved_path in resolve_extra_file_paths(package_path, &structured_file.path)?
+                {
+                    increase_structured_file_version(
+                        &resolved_path,
+                        structured_file,
+                        new_version,
+                        dry_run,
+                    )?;
+                    updated_files.push(resolved_path);
+                }
+            }
+        }
+    }
+    Ok(updated_files)
 }
 
 #[cfg(test)]
@@ -52,7 +308,7 @@ mod tests {
     use std::fs;
     use tempfile::NamedTempFile;
 
-    use crate::DryRunConfig;
+    use crate::{DryRunConfig, ExtraFileType, StructuredExtraFile};
 
     #[test]
     fn test_increase_extra_files_version() {
@@ -79,13 +335,15 @@ mod tests {
             );
             fs::write(&file_path, content).unwrap();
 
-            let extra_files = vec![file_path.clone()];
+            let extra_files = vec![ExtraFile::Marker(file_path.clone())];
 
             increase_extra_files_version(
+                ".",
                 &extra_files,
                 new_version,
                 &DryRunConfig { is_dry_run: false },
-            );
+            )
+            .unwrap();
 
             let updated_content = fs::read_to_string(&file_path).unwrap();
 
@@ -99,4 +357,203 @@ mod tests {
             assert!(updated_content.contains("Other content"));
         }
     }
+
+    #[test]
+    fn test_increase_structured_files_version() {
+        let json_file = NamedTempFile::new().unwrap();
+        let json_content =
+            "{\n  \"expo\": {\n    \"name\": \"app\",\n    \"version\": \"1.2.3\"\n  }\n}\n";
+        fs::write(json_file.path(), json_content).unwrap();
+
+        let yaml_file = NamedTempFile::new().unwrap();
+        let yaml_content = "app:\n  version: 1.2.3 # released\n";
+        fs::write(yaml_file.path(), yaml_content).unwrap();
+
+        let structured_file = |path: &NamedTempFile, file_type: ExtraFileType| {
+            ExtraFile::Structured(StructuredExtraFile {
+                path: path.path().to_str().unwrap().to_string(),
+                file_type,
+                jsonpath: Some(if file_type == ExtraFileType::Json {
+                    "$.expo.version".to_string()
+                } else {
+                    "$.app.version".to_string()
+                }),
+            })
+        };
+        let extra_files = vec![
+            structured_file(&json_file, ExtraFileType::Json),
+            structured_file(&yaml_file, ExtraFileType::Yaml),
+        ];
+
+        increase_extra_files_version(
+            ".",
+            &extra_files,
+            "1.3.0",
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(json_file.path()).unwrap(),
+            json_content.replace("1.2.3", "1.3.0")
+        );
+        assert_eq!(
+            fs::read_to_string(yaml_file.path()).unwrap(),
+            yaml_content.replace("1.2.3", "1.3.0")
+        );
+    }
+
+    #[test]
+    fn test_comment_markers() {
+        let markers = VersionMarkers::new();
+        let cases = [
+            "VERSION = \"1.2.3\"  # x-releaser-version\n",
+            "export VERSION=1.2.3 # x-releaser-version\n",
+            "SELECT '1.2.3'; -- x-releaser-version\n",
+            "Version 1.2.3 <!-- x-releaser-version -->\n",
+            "const v = '1.2.3'; /* x-releaser-version */\n",
+            "version = 1.2.3 ; x-releaser-version\n",
+            "version: 1.2.3 # x-releaser-version\r\n",
+        ];
+
+        for line in cases {
+            assert_eq!(
+                markers.update(line, "2.0.0"),
+                line.replace("1.2.3", "2.0.0"),
+                "Failed to update {}",
+                line
+            );
+        }
+
+        // Every version before the marker is replaced
+        let line = "image: app:1.2.3 # also tags 1.2.3 # x-releaser-version\n";
+        assert_eq!(
+            markers.update(line, "2.0.0"),
+            "image: app:2.0.0 # also tags 2.0.0 # x-releaser-version\n"
+        );
+
+        let untouched = "ENV VERSION=1.2.3 # not a marker\n";
+        assert_eq!(markers.update(untouched, "2.0.0"), untouched);
+    }
+
+    #[test]
+    fn test_block_markers() {
+        let markers = VersionMarkers::new();
+        let contents = "# Install\n<!-- x-releaser-start -->\n```bash\nnpm install api@1.2.3\ncurl -L https://example.com/v1.2.3/api-1.2.3.tgz\n```\n<!-- x-releaser-end -->\nPrevious release: 1.2.2\n";
+
+        assert_eq!(
+            markers.update(contents, "1.3.0"),
+            contents.replace("1.2.3", "1.3.0")
+        );
+    }
+
+    #[test]
+    fn test_extra_files_globs_and_package_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let package_path = dir.path().join("packages/api");
+        fs::create_dir_all(package_path.join("src/v1")).unwrap();
+        fs::create_dir_all(package_path.join("src/v2")).unwrap();
+        let content = "export const VERSION = '1.0.0'; // x-releaser-version\n";
+        for file in ["src/v1/version.ts", "src/v2/version.ts", "index.js"] {
+            fs::write(package_path.join(file), content).unwrap();
+        }
+        let package_path = package_path.to_str().unwrap();
+
+        let extra_files = vec![
+            ExtraFile::Marker("{path}/src/**/version.ts".to_string()),
+            ExtraFile::Marker(format!("{}/index.js", package_path)),
+        ];
+        increase_extra_files_version(
+            package_path,
+            &extra_files,
+            "1.1.0",
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap();
+
+        for file in ["src/v1/version.ts", "src/v2/version.ts", "index.js"] {
+            assert_eq!(
+                fs::read_to_string(Path::new(package_path).join(file)).unwrap(),
+                content.replace("1.0.0", "1.1.0")
+            );
+        }
+
+        let missing_file = vec![ExtraFile::Marker("{path}/missing.js".to_string())];
+        let error = increase_extra_files_version(
+            package_path,
+            &missing_file,
+            "1.1.0",
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Extra file {}/missing.js not found", package_path)
+        );
+
+        // `./` stays relative to the repository root
+        let missing_file = vec![ExtraFile::Marker("./missing.js".to_string())];
+        let error = increase_extra_files_version(
+            package_path,
+            &missing_file,
+            "1.1.0",
+            &DryRunConfig { is_dry_run: false },
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Extra file ./missing.js not found");
+    }
+
+    #[test]
+    fn test_templated_markers() {
+        let markers = VersionMarkers::new();
+        let cases = [
+            (
+                "[![version](https://img.shields.io/badge/api-v2-blue)](#) <!-- x-releaser-version:v{major} -->\n",
+                "-v2-",
+                "-v3-",
+            ),
+            (
+                "See https://docs.example.com/2.3/setup # x-releaser-version:{major}.{minor}\n",
+                "/2.3/",
+                "/3.1/",
+            ),
+            (
+                "FROM registry.example.com/api:2.3.4 # x-releaser-version:{version}\n",
+                "2.3.4",
+                "3.1.0",
+            ),
+            (
+                "image: api:2 # x-releaser-version:{major}\n",
+                ":2 ",
+                ":3 ",
+            ),
+        ];
+
+        for (line, old_token, new_token) in cases {
+            assert_eq!(
+                markers.update(line, "3.1.0"),
+                line.replace(old_token, new_token),
+                "Failed to update {}",
+                line
+            );
+        }
+
+        // Other numbers of the line are left alone
+        let line = "MAX_RETRIES = 3; const MAJOR = 1; // x-releaser-version:{major}\n";
+        assert_eq!(
+            markers.update(line, "2.0.0"),
+            "MAX_RETRIES = 3; const MAJOR = 2; // x-releaser-version:{major}\n"
+        );
+        let line = "Tested on 1.20.3, see docs/1.2 # x-releaser-version:{major}.{minor}\n";
+        assert_eq!(
+            markers.update(line, "2.0.0"),
+            "Tested on 1.20.3, see docs/2.0 # x-releaser-version:{major}.{minor}\n"
+        );
+
+        let block = "<!-- x-releaser-start:{major}.{minor} -->\nhttps://docs.example.com/2.3/install\n<!-- x-releaser-end -->\n";
+        assert_eq!(
+            markers.update(block, "3.1.0"),
+            block.replace("/2.3/", "/3.1/")
+        );
+    }
 }
@@ -0,0 +1,203 @@
+use serde_json::Value;
+use std::ops::Range;
+
+/// Minimal JSON scanner locating the bytes of a value, so that it can be
+/// replaced without reformatting the rest of the document.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!(
+                "Invalid JSON: expected '{}' at byte {}",
+                byte as char, self.pos
+            ))
+        }
+    }
+
+    fn string(&mut self) -> Result<Range<usize>, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        self.expect(b'"')?;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => return Ok(start..self.pos),
+                _ => {}
+            }
+        }
+        Err("Invalid JSON: unterminated string".to_string())
+    }
+
+    fn value(&mut self) -> Result<Range<usize>, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.bytes.get(self.pos) {
+            Some(b'"') => self.string(),
+            Some(b'{') | Some(b'[') => {
+                let is_object = self.bytes[self.pos] == b'{';
+                self.pos += 1;
+                self.skip_whitespace();
+                let end = if is_object { b'}' } else { b']' };
+                if self.bytes.get(self.pos) == Some(&end) {
+                    self.pos += 1;
+                    return Ok(start..self.pos);
+                }
+                loop {
+                    if is_object {
+                        self.string()?;
+                        self.expect(b':')?;
+                    }
+                    self.value()?;
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        Some(&byte) if byte == end => {
+                            self.pos += 1;
+                            return Ok(start..self.pos);
+                        }
+                        _ => return Err(format!("Invalid JSON at byte {}", self.pos)),
+                    }
+                }
+            }
+            Some(_) => {
+                while let Some(&byte) = self.bytes.get(self.pos) {
+                    if byte == b',' || byte == b'}' || byte == b']' || byte.is_ascii_whitespace() {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                Ok(start..self.pos)
+            }
+            None => Err("Invalid JSON: unexpected end of input".to_string()),
+        }
+    }
+
+    fn find(&mut self, path: &[&str]) -> Result<Option<Range<usize>>, String> {
+        self.skip_whitespace();
+        let Some((segment, rest)) = path.split_first() else {
+            return self.value().map(Some);
+        };
+
+        match self.bytes.get(self.pos) {
+            Some(b'{') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b'}') {
+                    return Ok(None);
+                }
+                loop {
+                    let key_range = self.string()?;
+                    let raw_key = std::str::from_utf8(&self.bytes[key_range]).unwrap_or("");
+                    let key: String = serde_json::from_str(raw_key).map_err(|e| e.to_string())?;
+                    self.expect(b':')?;
+                    if key == *segment {
+                        return self.find(rest);
+                    }
+                    self.value()?;
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        _ => return Ok(None),
+                    }
+                }
+            }
+            Some(b'[') => {
+                let Ok(index) = segment.parse::<usize>() else {
+                    return Ok(None);
+                };
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.bytes.get(self.pos) == Some(&b']') {
+                    return Ok(None);
+                }
+                for current in 0.. {
+                    if current == index {
+                        return self.find(rest);
+                    }
+                    self.value()?;
+                    self.skip_whitespace();
+                    match self.bytes.get(self.pos) {
+                        Some(b',') => self.pos += 1,
+                        _ => return Ok(None),
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Returns the byte range of the value found at `path` (object keys or array indexes).
+pub fn find_json_value(
+    content: &str,
+    path: &[&str],
+) -> Result<Option<Range<usize>>, Box<dyn std::error::Error>> {
+    let mut scanner = Scanner {
+        bytes: content.as_bytes(),
+        pos: 0,
+    };
+    Ok(scanner.find(path)?)
+}
+
+/// Replaces the value found at `path`, leaving every other byte of the document untouched.
+pub fn replace_json_value(
+    content: &str,
+    path: &[&str],
+    new_value: &Value,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let range = find_json_value(content, path)?
+        .ok_or_else(|| format!("No value found at {}", path.join(".")))?;
+
+    let mut new_content = content.to_string();
+    new_content.replace_range(range, &serde_json::to_string(new_value)?);
+    Ok(new_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_json_value_preserves_formatting() {
+        let content = "{\r\n\t\"name\": \"api\",\r\n\t\"scripts\": { \"version\": \"echo \\\"1.0.0\\\"\" },\r\n\t\"version\"  :  \"1.0.0\"\r\n}";
+        let updated = replace_json_value(content, &["version"], &Value::from("1.1.0")).unwrap();
+        assert_eq!(
+            updated,
+            content.replace("\"version\"  :  \"1.0.0\"", "\"version\"  :  \"1.1.0\"")
+        );
+    }
+
+    #[test]
+    fn test_replace_nested_json_value() {
+        let content = "{\n    \"expo\": {\n        \"name\": \"app\",\n        \"version\": \"2.3.0\",\n        \"plugins\": [\"a\", {\"version\": 3}]\n    }\n}\n";
+        let updated =
+            replace_json_value(content, &["expo", "version"], &Value::from("2.4.0")).unwrap();
+        assert_eq!(updated, content.replace("2.3.0", "2.4.0"));
+
+        let updated = replace_json_value(
+            content,
+            &["expo", "plugins", "1", "version"],
+            &Value::from(4),
+        )
+        .unwrap();
+        assert_eq!(updated, content.replace("\"version\": 3", "\"version\": 4"));
+
+        assert!(replace_json_value(content, &["expo", "missing"], &Value::from(1)).is_err());
+    }
+}
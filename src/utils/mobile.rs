@@ -0,0 +1,129 @@
+use regex::{Captures, Regex};
+
+fn increment_build_number(
+    contents: &str,
+    pattern: &Regex,
+    file_name: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let captures = pattern
+        .captures(contents)
+        .ok_or_else(|| format!("No build number found in {}", file_name))?;
+    let build_number = captures[2].trim().parse::<u64>().map_err(|_| {
+        format!(
+            "The build number {} of {} is not an integer",
+            &captures[2], file_name
+        )
+    })?;
+
+    let range = captures.get(2).unwrap().range();
+    let mut new_contents = contents.to_string();
+    new_contents.replace_range(range, &(build_number + 1).to_string());
+    Ok(new_contents)
+}
+
+fn replace_version_name(contents: &str, pattern: &Regex, new_version: &str) -> String {
+    pattern
+        .replace(contents, |captures: &Captures| {
+            format!("{}{}{}", &captures[1], new_version, &captures[3])
+        })
+        .to_string()
+}
+
+/// Increments `versionCode` and sets `versionName` in an Android `build.gradle`
+/// (Groovy or Kotlin DSL).
+pub fn update_gradle_versions(
+    contents: &str,
+    new_version: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let version_code = Regex::new(r"(versionCode\s*=?\s*)(\d+)").unwrap();
+    let version_name = Regex::new(r#"(versionName\s*=?\s*["'])([^"']*)(["'])"#).unwrap();
+
+    let new_contents = increment_build_number(contents, &version_code, "build.gradle")?;
+    Ok(replace_version_name(
+        &new_contents,
+        &version_name,
+        new_version,
+    ))
+}
+
+/// Increments `CFBundleVersion` and sets `CFBundleShortVersionString` in an iOS `Info.plist`.
+pub fn update_plist_versions(
+    contents: &str,
+    new_version: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let bundle_version =
+        Regex::new(r"(<key>CFBundleVersion</key>\s*<string>)([^<]*)(</string>)").unwrap();
+    let short_version =
+        Regex::new(r"(<key>CFBundleShortVersionString</key>\s*<string>)([^<]*)(</string>)")
+            .unwrap();
+
+    for (key, pattern) in [
+        ("CFBundleVersion", &bundle_version),
+        ("CFBundleShortVersionString", &short_version),
+    ] {
+        if let Some(captures) = pattern.captures(contents) {
+            if captures[2].trim().starts_with("$(") {
+                return Err(format!(
+                    "{} in Info.plist is the build setting {}, update it in the Xcode project instead",
+                    key,
+                    captures[2].trim()
+                )
+                .into());
+            }
+        }
+    }
+
+    let new_contents = increment_build_number(contents, &bundle_version, "Info.plist")?;
+    Ok(replace_version_name(
+        &new_contents,
+        &short_version,
+        new_version,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_gradle_versions() {
+        let groovy = "android {\n    defaultConfig {\n        versionCode 41\n        versionName \"1.2.0\"\n    }\n}\n";
+        assert_eq!(
+            update_gradle_versions(groovy, "1.3.0").unwrap(),
+            groovy
+                .replace("versionCode 41", "versionCode 42")
+                .replace("\"1.2.0\"", "\"1.3.0\"")
+        );
+
+        let kotlin = "defaultConfig {\n    versionCode = 9\n    versionName = \"0.9.0\"\n}\n";
+        assert_eq!(
+            update_gradle_versions(kotlin, "1.0.0").unwrap(),
+            kotlin
+                .replace("versionCode = 9", "versionCode = 10")
+                .replace("\"0.9.0\"", "\"1.0.0\"")
+        );
+    }
+
+    #[test]
+    fn test_update_plist_versions() {
+        let plist = "<dict>\n\t<key>CFBundleShortVersionString</key>\n\t<string>1.2.0</string>\n\t<key>CFBundleVersion</key>\n\t<string>99</string>\n</dict>\n";
+        assert_eq!(
+            update_plist_versions(plist, "1.3.0").unwrap(),
+            plist
+                .replace("<string>1.2.0</string>", "<string>1.3.0</string>")
+                .replace("<string>99</string>", "<string>100</string>")
+        );
+
+        let variable = "<key>CFBundleVersion</key>\n<string>$(CURRENT_PROJECT_VERSION)</string>\n";
+        assert_eq!(
+            update_plist_versions(variable, "1.3.0").unwrap_err().to_string(),
+            "CFBundleVersion in Info.plist is the build setting $(CURRENT_PROJECT_VERSION), update it in the Xcode project instead"
+        );
+
+        let variable = plist.replace("1.2.0", "$(MARKETING_VERSION)");
+        assert_eq!(
+            update_plist_versions(&variable, "1.3.0").unwrap_err().to_string(),
+            "CFBundleShortVersionString in Info.plist is the build setting $(MARKETING_VERSION), update it in the Xcode project instead"
+        );
+    }
+}
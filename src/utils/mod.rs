@@ -1,2 +1,8 @@
 pub use self::file_utils::increase_extra_files_version;
+pub use self::json_edit::{find_json_value, replace_json_value};
+pub use self::mobile::{update_gradle_versions, update_plist_versions};
+pub use self::structured_edit::{parse_jsonpath, replace_toml_value, replace_yaml_value};
 mod file_utils;
+mod json_edit;
+mod mobile;
+mod structured_edit;
@@ -0,0 +1,188 @@
+use std::ops::Range;
+use toml_edit::{DocumentMut, Value};
+
+/// Splits a JSONPath such as `$.expo.version`, `$.plugins[0].version` or
+/// `$['my.key'].version` into its keys.
+pub fn parse_jsonpath(jsonpath: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut keys = Vec::new();
+    let mut rest = jsonpath.trim().strip_prefix('$').unwrap_or(jsonpath.trim());
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            keys.push(after_dot[..end].to_string());
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket
+                .find(']')
+                .ok_or_else(|| format!("Unclosed bracket in JSONPath {}", jsonpath))?;
+            let key = after_bracket[..end].trim_matches(|c| c == '\'' || c == '"');
+            keys.push(key.to_string());
+            rest = &after_bracket[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            keys.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+    }
+
+    if keys.is_empty() || keys.iter().any(|key| key.is_empty()) {
+        return Err(format!("Invalid JSONPath {}", jsonpath).into());
+    }
+    Ok(keys)
+}
+
+/// Returns the byte range of the scalar at `path`, without its quotes. Only block
+/// mappings are supported, which covers the version fields of YAML config files.
+fn find_yaml_value(content: &str, path: &[&str]) -> Option<Range<usize>> {
+    let mut parents: Vec<(usize, &str)> = Vec::new();
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with("---") {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let Some((raw_key, raw_value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        if !raw_value.is_empty() && !raw_value.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        while parents
+            .last()
+            .is_some_and(|(parent_indent, _)| *parent_indent >= indent)
+        {
+            parents.pop();
+        }
+        let key = raw_key.trim().trim_matches(|c| c == '"' || c == '\'');
+        parents.push((indent, key));
+
+        let is_match = parents.len() == path.len()
+            && parents
+                .iter()
+                .zip(path)
+                .all(|((_, key), segment)| key == segment);
+        if !is_match {
+            continue;
+        }
+
+        let value = raw_value.split(" #").next().unwrap_or("").trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        if value.is_empty() {
+            return None;
+        }
+        let raw_value_start = line_offset + indent + raw_key.len() + 1;
+        let value_start = raw_value_start + raw_value.find(value)?;
+        return Some(value_start..value_start + value.len());
+    }
+    None
+}
+
+/// Replaces the scalar at `path`, keeping its quotes and every other byte of the file.
+pub fn replace_yaml_value(
+    content: &str,
+    path: &[&str],
+    new_value: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let range = find_yaml_value(content, path)
+        .ok_or_else(|| format!("No value found at {}", path.join(".")))?;
+
+    let mut new_content = content.to_string();
+    new_content.replace_range(range, new_value);
+    Ok(new_content)
+}
+
+/// Replaces the string at `path`, keeping the whitespace and comments around it.
+pub fn replace_toml_value(
+    content: &str,
+    path: &[&str],
+    new_value: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut document = content.parse::<DocumentMut>()?;
+
+    let (last, parents) = path
+        .split_last()
+        .ok_or("An empty TOML path cannot be replaced")?;
+    let mut item = document.as_item_mut();
+    for key in parents {
+        item = item
+            .get_mut(key)
+            .ok_or_else(|| format!("No value found at {}", path.join(".")))?;
+    }
+    let value = item
+        .get_mut(last)
+        .and_then(|item| item.as_value_mut())
+        .ok_or_else(|| format!("No value found at {}", path.join(".")))?;
+
+    // Keep literal strings, such as `version = '1.2.3'`, in single quotes
+    let is_literal = matches!(value, Value::String(string) if string
+        .as_repr()
+        .and_then(|repr| repr.as_raw().as_str())
+        .is_some_and(|raw| raw.starts_with('\'')));
+    let literal = format!("'{}'", new_value).parse::<Value>();
+
+    let decor = value.decor().clone();
+    *value = match literal {
+        Ok(literal) if is_literal => literal,
+        _ => Value::from(new_value),
+    };
+    *value.decor_mut() = decor;
+
+    Ok(document.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_jsonpath() {
+        assert_eq!(
+            parse_jsonpath("$.expo.version").unwrap(),
+            vec!["expo", "version"]
+        );
+        assert_eq!(
+            parse_jsonpath("$.plugins[0]['my.key']").unwrap(),
+            vec!["plugins", "0", "my.key"]
+        );
+        assert_eq!(parse_jsonpath("version").unwrap(), vec!["version"]);
+        assert!(parse_jsonpath("$").is_err());
+    }
+
+    #[test]
+    fn test_replace_yaml_value() {
+        let content = "# App config\napp:\n  name: web\n  version: \"1.2.3\" # current\nimage:\n  version: 1.2.3\n";
+        let updated = replace_yaml_value(content, &["app", "version"], "1.3.0").unwrap();
+        assert_eq!(
+            updated,
+            content.replace("\"1.2.3\" # current", "\"1.3.0\" # current")
+        );
+
+        let updated = replace_yaml_value(content, &["image", "version"], "1.3.0").unwrap();
+        assert_eq!(
+            updated,
+            content.replace("  version: 1.2.3\n", "  version: 1.3.0\n")
+        );
+
+        assert!(replace_yaml_value(content, &["version"], "1.3.0").is_err());
+    }
+
+    #[test]
+    fn test_replace_toml_value() {
+        let content = "[package]\nname = \"cli\"\nversion = \"1.2.3\" # bumped by releaser\n\n[metadata.app]\nversion = '1.2.3'\n";
+        let updated = replace_toml_value(content, &["package", "version"], "1.3.0").unwrap();
+        assert_eq!(updated, content.replacen("\"1.2.3\"", "\"1.3.0\"", 1));
+
+        let updated =
+            replace_toml_value(content, &["metadata", "app", "version"], "1.3.0").unwrap();
+        assert_eq!(updated, content.replace("'1.2.3'", "'1.3.0'"));
+
+        assert!(replace_toml_value(content, &["package", "missing"], "1.3.0").is_err());
+    }
+}
@@ -1,2 +1,4 @@
+pub use self::pep440::{from_pep440, to_pep440};
 pub use self::semver::{increase_version, semver_compare, Semver};
+mod pep440;
 mod semver;
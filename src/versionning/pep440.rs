@@ -0,0 +1,66 @@
+use regex::Regex;
+
+/// Converts a releaser version (`1.2.0-beta.1`) to its PEP 440 form (`1.2.0b1`).
+pub fn to_pep440(version: &str) -> String {
+    let captures = version.splitn(2, '-').collect::<Vec<&str>>();
+    if captures.len() < 2 {
+        return version.to_string();
+    }
+
+    let mut pre_parts = captures[1].splitn(2, '.');
+    let label = pre_parts.next().unwrap_or("");
+    let number = pre_parts
+        .next()
+        .and_then(|n| n.parse::<u32>().ok())
+        .unwrap_or(0);
+
+    let pep440_label = match label {
+        "alpha" | "a" => "a",
+        "beta" | "b" => "b",
+        "rc" => "rc",
+        _ => return version.to_string(),
+    };
+
+    format!("{}{}{}", captures[0], pep440_label, number)
+}
+
+/// Converts a PEP 440 version (`1.2.0b1`) back to the releaser form (`1.2.0-beta.1`).
+pub fn from_pep440(version: &str) -> String {
+    let re = Regex::new(r"^(\d+\.\d+\.\d+)(a|b|rc)(\d+)$").unwrap();
+
+    match re.captures(version) {
+        Some(captures) => {
+            let label = match &captures[2] {
+                "a" => "alpha",
+                "b" => "beta",
+                _ => "rc",
+            };
+            format!("{}-{}.{}", &captures[1], label, &captures[3])
+        }
+        None => version.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_pep440() {
+        assert_eq!(to_pep440("1.2.0"), "1.2.0");
+        assert_eq!(to_pep440("1.2.0-beta"), "1.2.0b0");
+        assert_eq!(to_pep440("1.2.0-beta.1"), "1.2.0b1");
+        assert_eq!(to_pep440("1.2.0-alpha.3"), "1.2.0a3");
+        assert_eq!(to_pep440("1.2.0-rc.2"), "1.2.0rc2");
+    }
+
+    #[test]
+    fn test_from_pep440() {
+        assert_eq!(from_pep440("1.2.0"), "1.2.0");
+        assert_eq!(from_pep440("1.2.0b0"), "1.2.0-beta.0");
+        assert_eq!(from_pep440("1.2.0b1"), "1.2.0-beta.1");
+        assert_eq!(from_pep440("1.2.0a3"), "1.2.0-alpha.3");
+        assert_eq!(from_pep440("1.2.0rc2"), "1.2.0-rc.2");
+        assert_eq!(from_pep440("1.2.0.post1"), "1.2.0.post1");
+    }
+}